@@ -0,0 +1,206 @@
+use bevy::{ecs::query::QueryData, prelude::*};
+
+use crate::{
+    player::player::Player,
+    vessels::{
+        damage::Hull,
+        orders::{Order, OrderEvent, OrderQueue},
+        subsystems::Subsystems,
+        targeting::{hostiles_in_range, sensor_range},
+        vessels::{VesselDefinition, VesselID},
+    },
+};
+
+use super::doctrine::{AiDifficulty, Doctrine};
+
+/// Multiple of its sensor range a fleeing vessel runs from the nearest hostile
+const FLEE_DISTANCE: f32 = 1.5;
+
+/// What an AI vessel is doing, each behaviour is carried out through orders to its autopilot
+#[derive(Clone, PartialEq, Debug)]
+pub enum Behaviour {
+    /// Fly the patrol route while no hostile is in sight
+    Patrol,
+    Engage(Entity),
+    /// Run from hostiles after taking too much damage
+    Flee,
+    /// Rejoin the rest of the fleet
+    Regroup,
+}
+
+/// Decides what a vessel of an AI player does
+#[derive(Component)]
+pub struct AiController {
+    /// `None` until the first decision
+    pub behaviour: Option<Behaviour>,
+    decision_timer: Timer,
+    patrol_route: Vec<Vec3>,
+}
+
+pub struct AiPlugin;
+impl Plugin for AiPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(AiDifficulty::from_args())
+            .add_systems(Update, (attach_ai, decide).chain());
+    }
+}
+
+/// Hands every vessel spawned for an AI player to an [`AiController`] patrolling around its spawn point
+fn attach_ai(
+    mut commands: Commands,
+    difficulty: Res<AiDifficulty>,
+    vessels: Query<(Entity, &VesselID, &Transform, &VesselDefinition), Added<VesselID>>,
+) {
+    for (entity, vessel_id, transform, vessel_definition) in vessels.iter() {
+        let Player::AI(_) = vessel_id.player else {
+            continue;
+        };
+        let patrol_radius = Doctrine::of(&vessel_definition.faction).patrol_radius;
+        let patrol_route = [
+            Vec3::new(1.0, 0.0, 1.0),
+            Vec3::new(1.0, 0.0, -1.0),
+            Vec3::new(-1.0, 0.0, -1.0),
+            Vec3::new(-1.0, 0.0, 1.0),
+        ]
+        .map(|corner| transform.translation + corner * patrol_radius)
+        .to_vec();
+        commands.entity(entity).insert(AiController {
+            behaviour: None,
+            decision_timer: Timer::from_seconds(difficulty.reaction_time, TimerMode::Repeating),
+            patrol_route,
+        });
+    }
+}
+
+/// A vessel under AI control with what its decisions are based on
+#[derive(QueryData)]
+#[query_data(mutable)]
+struct AiVessel {
+    vessel_id: &'static VesselID,
+    transform: &'static Transform,
+    vessel_definition: &'static VesselDefinition,
+    hull: &'static Hull,
+    subsystems: Option<&'static Subsystems>,
+    order_queue: &'static OrderQueue,
+    controller: &'static mut AiController,
+}
+
+/// Picks each AI vessel's behaviour once per reaction time and gives the orders carrying it out
+/// when the behaviour changes or the previous orders are done
+fn decide(
+    time: Res<Time>,
+    difficulty: Res<AiDifficulty>,
+    mut order_events: EventWriter<OrderEvent>,
+    mut controllers: Query<AiVessel>,
+    vessels: Query<(Entity, &VesselID, &Transform)>,
+) {
+    // Targets engaged by each AI player's fleet, for focus fire
+    let engaged: Vec<(Player, Entity)> = controllers
+        .iter()
+        .filter_map(|ai_vessel| match ai_vessel.controller.behaviour {
+            Some(Behaviour::Engage(target)) => Some((ai_vessel.vessel_id.player.clone(), target)),
+            _ => None,
+        })
+        .collect();
+
+    for AiVesselItem {
+        vessel_id,
+        transform,
+        vessel_definition,
+        hull,
+        subsystems,
+        order_queue,
+        mut controller,
+    } in controllers.iter_mut()
+    {
+        if difficulty.is_changed() {
+            controller
+                .decision_timer
+                .set_duration(std::time::Duration::from_secs_f32(difficulty.reaction_time));
+        }
+        if !controller.decision_timer.tick(time.delta()).just_finished() {
+            continue;
+        }
+        let doctrine = Doctrine::of(&vessel_definition.faction);
+        let position = transform.translation;
+        let sensor_range = sensor_range(vessel_definition, subsystems);
+        let in_sensor_range = hostiles_in_range(vessel_id, position, sensor_range, vessels.iter());
+        let noticed: Vec<Entity> = in_sensor_range
+            .iter()
+            .filter(|(_, distance)| *distance <= sensor_range * difficulty.awareness)
+            .map(|(entity, _)| *entity)
+            .collect();
+        let fleet: Vec<Vec3> = vessels
+            .iter()
+            .filter(|(_, other_vessel_id, _)| {
+                other_vessel_id.player == vessel_id.player && *other_vessel_id != vessel_id
+            })
+            .map(|(_, _, other_transform)| other_transform.translation)
+            .collect();
+
+        let current_target = match controller.behaviour {
+            Some(Behaviour::Engage(target)) => Some(target),
+            _ => None,
+        };
+        // Stay on the current target until it is destroyed or has run out of pursuit range
+        let pursued = current_target.filter(|target| {
+            vessels.get(*target).is_ok_and(|(_, _, target_transform)| {
+                target_transform.translation.distance(position)
+                    <= sensor_range * doctrine.pursuit_range
+            })
+        });
+        let focus_target = doctrine
+            .focus_fire
+            .then(|| {
+                noticed.iter().copied().find(|hostile| {
+                    engaged
+                        .iter()
+                        .any(|(player, target)| *player == vessel_id.player && target == hostile)
+                })
+            })
+            .flatten();
+
+        let behaviour = if hull.integrity < hull.max_integrity * doctrine.flee_below_hull
+            && !in_sensor_range.is_empty()
+        {
+            Behaviour::Flee
+        } else if let Some(target) = pursued.or(focus_target).or(noticed.first().copied()) {
+            Behaviour::Engage(target)
+        } else if !fleet.is_empty()
+            && position.distance(fleet.iter().sum::<Vec3>() / fleet.len() as f32)
+                > doctrine.regroup_distance
+        {
+            Behaviour::Regroup
+        } else {
+            Behaviour::Patrol
+        };
+
+        if controller.behaviour.as_ref() == Some(&behaviour) && !order_queue.0.is_empty() {
+            continue;
+        }
+        let order = match &behaviour {
+            Behaviour::Patrol => Order::Patrol {
+                waypoints: controller.patrol_route.clone(),
+                next: 0,
+            },
+            Behaviour::Engage(target) => Order::Attack(*target),
+            Behaviour::Flee => {
+                let away = in_sensor_range
+                    .first()
+                    .and_then(|(nearest, _)| vessels.get(*nearest).ok())
+                    .and_then(|(_, _, nearest_transform)| {
+                        (position - nearest_transform.translation).try_normalize()
+                    })
+                    .unwrap_or(transform.rotation * Vec3::X);
+                Order::MoveTo(position + away * sensor_range * FLEE_DISTANCE)
+            }
+            Behaviour::Regroup => Order::MoveTo(fleet.iter().sum::<Vec3>() / fleet.len() as f32),
+        };
+        order_events.send(OrderEvent {
+            vessel_id: vessel_id.clone(),
+            order,
+            queue: false,
+        });
+        controller.behaviour = Some(behaviour);
+    }
+}
@@ -0,0 +1,79 @@
+use bevy::prelude::*;
+
+use crate::vessels::vessels::Faction;
+
+/// How the vessels of a faction fight, whichever AI player commands them
+pub struct Doctrine {
+    /// Fraction of hull integrity below which a vessel breaks off and flees from hostiles
+    pub flee_below_hull: f32,
+    /// Distance from the rest of its fleet beyond which an idle vessel rejoins it
+    pub regroup_distance: f32,
+    /// Multiple of its sensor range up to which a vessel chases its target before giving up
+    pub pursuit_range: f32,
+    /// Join the attack on a target the fleet is already engaging rather than picking the nearest
+    pub focus_fire: bool,
+    /// Half the side of the square a vessel patrols around where it was spawned
+    pub patrol_radius: f32,
+}
+impl Doctrine {
+    pub fn of(faction: &Faction) -> Self {
+        match faction {
+            Faction::Greek => Doctrine {
+                flee_below_hull: 0.25,
+                regroup_distance: 600.0,
+                pursuit_range: 1.5,
+                focus_fire: true,
+                patrol_radius: 400.0,
+            },
+        }
+    }
+}
+
+/// Skill of the AI opponents, shared by all AI players
+#[derive(Resource, Clone)]
+pub struct AiDifficulty {
+    /// Seconds between two decisions of an AI vessel
+    pub reaction_time: f32,
+    /// Fraction of its sensor range within which an AI vessel notices hostiles
+    pub awareness: f32,
+}
+impl AiDifficulty {
+    /// Difficulty chosen with the `--difficulty easy|normal|hard` startup argument, normal without one
+    pub fn from_args() -> Self {
+        let name = std::env::args()
+            .skip_while(|arg| arg != "--difficulty")
+            .nth(1);
+        match name.as_deref() {
+            Some("easy") => AiDifficulty::easy(),
+            Some("hard") => AiDifficulty::hard(),
+            Some("normal") | None => AiDifficulty::normal(),
+            Some(name) => {
+                warn!("unknown difficulty {}, playing on normal", name);
+                AiDifficulty::normal()
+            }
+        }
+    }
+    pub fn easy() -> Self {
+        AiDifficulty {
+            reaction_time: 2.0,
+            awareness: 0.5,
+        }
+    }
+    pub fn normal() -> Self {
+        AiDifficulty {
+            reaction_time: 1.0,
+            awareness: 0.8,
+        }
+    }
+    pub fn hard() -> Self {
+        AiDifficulty {
+            reaction_time: 0.3,
+            awareness: 1.0,
+        }
+    }
+}
+impl Default for AiDifficulty {
+    fn default() -> Self {
+        AiDifficulty::normal()
+    }
+}
@@ -0,0 +1,2 @@
+pub mod behaviour;
+pub mod doctrine;
@@ -1,9 +1,19 @@
+use std::f32::consts::PI;
+
 use bevy::{
     asset::LoadState,
     core_pipeline::Skybox,
     prelude::*,
     render::render_resource::{TextureViewDescriptor, TextureViewDimension},
 };
+use bevy_rapier3d::prelude::*;
+
+use crate::vessels::navigation::Obstacle;
+
+/// Centre of the dense asteroid field vessels navigate around as a whole
+const ASTEROID_FIELD_CENTRE: Vec3 = Vec3::new(-3000.0, 0.0, -3000.0);
+const ASTEROID_FIELD_RADIUS: f32 = 600.0;
+const ASTEROID_FIELD_COUNT: usize = 28;
 
 pub struct SolarSystemPlugin;
 impl Plugin for SolarSystemPlugin {
@@ -26,21 +36,37 @@ fn solar_system_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
         },
         ..default()
     });
-    commands.spawn((SceneBundle {
-        scene: asset_server.load("3D/environment/planet.glb#Scene0"),
-        transform: Transform::from_xyz(0.0, 2.0, 6_772_000.0)
-            .with_rotation(Quat::from_rotation_y((75.0_f32).to_radians()))
-            .with_scale(Vec3::splat(1000.0)),
-        ..default()
-    },));
-    let parent: Entity = commands
-        .spawn((SceneBundle {
-            scene: asset_server.load("3D/environment/asteroid_01.glb#Scene0"),
-            transform: Transform::from_xyz(-5000.0, 2.0, 5.0).with_scale(Vec3::splat(10.0)),
-            // transform: Transform::from_scale(Vec3::splat(0.5)),
+    let planet_transform = Transform::from_xyz(0.0, 2.0, 6_772_000.0)
+        .with_rotation(Quat::from_rotation_y((75.0_f32).to_radians()))
+        .with_scale(Vec3::splat(1000.0));
+    let planet_collider = Collider::ball(1.0);
+    commands.spawn((
+        SceneBundle {
+            scene: asset_server.load("3D/environment/planet.glb#Scene0"),
+            transform: planet_transform,
             ..default()
-        },))
+        },
+        RigidBody::Fixed,
+        Obstacle::enclosing(&planet_collider, &planet_transform),
+        planet_collider,
+    ));
+    let asteroid_transform = Transform::from_xyz(-5000.0, 2.0, 5.0).with_scale(Vec3::splat(10.0));
+    // the asteroid model spans roughly one unit from its origin
+    let asteroid_collider = Collider::ball(0.9);
+    let parent: Entity = commands
+        .spawn((
+            SceneBundle {
+                scene: asset_server.load("3D/environment/asteroid_01.glb#Scene0"),
+                transform: asteroid_transform,
+                // transform: Transform::from_scale(Vec3::splat(0.5)),
+                ..default()
+            },
+            RigidBody::Fixed,
+            Obstacle::enclosing(&asteroid_collider, &asteroid_transform),
+            asteroid_collider,
+        ))
         .id();
+    spawn_asteroid_field(&mut commands, &asset_server);
 
     let parent: Entity = commands
         .spawn((SceneBundle {
@@ -53,3 +79,39 @@ fn solar_system_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
         },))
         .id();
 }
+
+/// Scatters asteroids evenly through a sphere along a golden-angle spiral, the field is a single obstacle
+/// enclosing all of them rather than one per asteroid
+fn spawn_asteroid_field(commands: &mut Commands, asset_server: &AssetServer) {
+    let golden_angle = PI * (3.0 - 5.0_f32.sqrt());
+    let mut field_radius: f32 = 0.0;
+    for i in 0..ASTEROID_FIELD_COUNT {
+        let fraction = (i as f32 + 0.5) / ASTEROID_FIELD_COUNT as f32;
+        let height = 1.0 - 2.0 * fraction;
+        let ring = (1.0 - height * height).sqrt();
+        let azimuth = golden_angle * i as f32;
+        let direction = Vec3::new(ring * azimuth.cos(), height, ring * azimuth.sin());
+        let distance = ASTEROID_FIELD_RADIUS * fraction.cbrt();
+        let transform = Transform::from_translation(ASTEROID_FIELD_CENTRE + direction * distance)
+            .with_rotation(Quat::from_euler(EulerRot::YXZ, azimuth, height, 0.0))
+            .with_scale(Vec3::splat(5.0 + 15.0 * (azimuth.sin() * 0.5 + 0.5)));
+        let collider = Collider::ball(0.9);
+        field_radius =
+            field_radius.max(distance + Obstacle::enclosing(&collider, &transform).radius);
+        commands.spawn((
+            SceneBundle {
+                scene: asset_server.load("3D/environment/asteroid_01.glb#Scene0"),
+                transform,
+                ..default()
+            },
+            RigidBody::Fixed,
+            collider,
+        ));
+    }
+    commands.spawn((
+        SpatialBundle::from_transform(Transform::from_translation(ASTEROID_FIELD_CENTRE)),
+        Obstacle {
+            radius: field_radius,
+        },
+    ));
+}
@@ -1,17 +1,25 @@
 //! A simple 3D scene with light shining over a cube sitting on a plane.
+mod ai;
 mod environment;
+mod physics;
 mod player;
 mod vessels;
-use std::collections::HashMap;
-
+use ai::behaviour::AiPlugin;
 use bevy::prelude::*;
 use environment::{skybox::SkyboxPlugin, solar_system::SolarSystemPlugin};
-use player::{camera::FlightCameraPlugin, input::InputParser};
+use physics::collisions::CollisionPlugin;
+use player::{camera::FlightCameraPlugin, hud::HudPlugin, input::InputParser};
 use vessels::{
-    movements::{MovementEvent, MovementProperties, VelocityVector, VesselMovement},
+    damage::DamagePlugin,
+    formations::FormationsPlugin,
+    loader::VesselDefinitionPlugin,
+    movements::{MovementEvent, VelocityVector, VesselMovement},
+    navigation::NavigationPlugin,
+    orders::OrdersPlugin,
     spawn::spawn_vessel,
-    vessels::{VesselDefinition, VesselID},
-    weapons::{Hardpoint, WeaponsPlugin, WeaponsType},
+    targeting::TargetingPlugin,
+    vessels::VesselID,
+    weapons::WeaponsPlugin,
 };
 fn main() {
     App::new()
@@ -23,7 +31,13 @@ fn main() {
             SkyboxPlugin,
             SolarSystemPlugin,
             WeaponsPlugin,
+            CollisionPlugin,
+            VesselDefinitionPlugin,
+            DamagePlugin,
+            TargetingPlugin,
+            HudPlugin,
         ))
+        .add_plugins((OrdersPlugin, AiPlugin, FormationsPlugin, NavigationPlugin))
         .add_event::<MovementEvent>()
         .add_systems(Startup, setup)
         .run();
@@ -41,81 +55,38 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
         transform: Transform::from_xyz(4.0, 8.0, 4.0),
         ..default()
     });
-    let player_vessel = VesselDefinition {
-        class: vessels::vessels::VesselClass::Cruiser,
-        faction: vessels::vessels::Faction::Greek,
-        model_path: "3D/vessels/greek/Myrmidon_Leviathan/vessel.glb".to_owned(),
-        movement_properties: MovementProperties {
-            linear_acceleration: Vec3 {
-                x: 100.0,
-                y: 5.0,
-                z: 5.0,
-            },
-            angular_acceleration: Vec3 {
-                x: 5.0,
-                y: 5.0,
-                z: 5.0,
-            },
-        },
-        hardpoints: vec![(
-            WeaponsType::Plasma,
-            vec![
-                Hardpoint {
-                    transform: Transform::from_translation(Vec3 {
-                        x: 10.0,
-                        y: 0.0,
-                        z: -1.80,
-                    }),
-                },
-                Hardpoint {
-                    transform: Transform::from_translation(Vec3 {
-                        x: 9.50,
-                        y: 0.0,
-                        z: -2.1,
-                    }),
-                },
-                Hardpoint {
-                    transform: Transform::from_translation(Vec3 {
-                        x: 9.0,
-                        y: 0.0,
-                        z: -2.40,
-                    }),
-                },
-                Hardpoint {
-                    transform: Transform::from_translation(Vec3 {
-                        x: 10.0,
-                        y: 0.0,
-                        z: 1.80,
-                    }),
-                },
-                Hardpoint {
-                    transform: Transform::from_translation(Vec3 {
-                        x: 9.50,
-                        y: 0.0,
-                        z: 2.10,
-                    }),
-                },
-                Hardpoint {
-                    transform: Transform::from_translation(Vec3 {
-                        x: 9.0,
-                        y: 0.0,
-                        z: 2.40,
-                    }),
-                },
-            ],
-        )]
-        .iter()
-        .cloned()
-        .collect(),
-    };
     spawn_vessel(
         &mut commands,
-        &asset_server,
-        &player_vessel,
+        asset_server.load("vessels/greek/myrmidon_leviathan.vessel.ron"),
+        VesselID {
+            player: player::player::Player::Host,
+            id: 0,
+        },
+        VelocityVector::default(),
+    );
+    let escort = spawn_vessel(
+        &mut commands,
+        asset_server.load("vessels/greek/myrmidon_leviathan.vessel.ron"),
         VesselID {
             player: player::player::Player::Host,
+            id: 1,
+        },
+        VelocityVector::default(),
+    );
+    commands
+        .entity(escort)
+        .insert(Transform::from_xyz(0.0, 0.0, 60.0));
+    // an opponent patrolling just outside sensor range
+    let opponent = spawn_vessel(
+        &mut commands,
+        asset_server.load("vessels/greek/myrmidon_leviathan.vessel.ron"),
+        VesselID {
+            player: player::player::Player::AI(0),
             id: 0,
         },
         VelocityVector::default(),
     );
+    commands
+        .entity(opponent)
+        .insert(Transform::from_xyz(4500.0, 0.0, 0.0));
 }
@@ -0,0 +1,108 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::vessels::{vessels::VesselID, weapons::Projectile};
+
+pub struct CollisionPlugin;
+impl Plugin for CollisionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            RapierPhysicsPlugin::<NoUserData>::default(),
+            RapierDebugRenderPlugin {
+                enabled: false,
+                ..default()
+            },
+        ))
+        .insert_resource(RapierConfiguration {
+            gravity: Vec3::ZERO,
+            ..RapierConfiguration::new(1.0)
+        })
+        .add_event::<VesselCollisionEvent>()
+        .add_event::<ProjectileImpactEvent>()
+        .add_systems(Update, (report_collisions, report_contact_forces));
+    }
+}
+
+/// A vessel struck another solid body, e.g. an asteroid or another vessel, hard enough to pass the
+/// contact force threshold of its collider
+#[derive(Event)]
+pub struct VesselCollisionEvent {
+    pub vessel: Entity,
+    pub other: Entity,
+    /// Impulse the bodies exchanged during the last physics step
+    pub impulse: f32,
+    /// World position of the deepest contact between the bodies
+    pub point: Vec3,
+}
+
+/// A projectile entered the collider of anything but the vessel that launched it or its other shots
+#[derive(Event)]
+pub struct ProjectileImpactEvent {
+    pub projectile: Entity,
+    pub target: Entity,
+}
+
+pub fn report_collisions(
+    mut collision_events: EventReader<CollisionEvent>,
+    mut projectile_impacts: EventWriter<ProjectileImpactEvent>,
+    projectiles: Query<&Projectile>,
+) {
+    for collision_event in collision_events.read() {
+        let CollisionEvent::Started(entity_a, entity_b, _) = *collision_event else {
+            continue;
+        };
+        for (entity, other) in [(entity_a, entity_b), (entity_b, entity_a)] {
+            if let Ok(projectile) = projectiles.get(entity) {
+                let same_launcher = projectiles
+                    .get(other)
+                    .is_ok_and(|other_projectile| other_projectile.launcher == projectile.launcher);
+                if projectile.launcher != other && !same_launcher {
+                    projectile_impacts.send(ProjectileImpactEvent {
+                        projectile: entity,
+                        target: other,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Reports the contact forces between vessels and other solid bodies. Rapier has already resolved the
+/// contact when its velocities are written back, the impulse it applied is what the impact was.
+pub fn report_contact_forces(
+    rapier_context: Res<RapierContext>,
+    mut contact_force_events: EventReader<ContactForceEvent>,
+    mut vessel_collisions: EventWriter<VesselCollisionEvent>,
+    vessels: Query<(), With<VesselID>>,
+    transforms: Query<&GlobalTransform>,
+) {
+    for contact_force_event in contact_force_events.read() {
+        let (entity_a, entity_b) = (contact_force_event.collider1, contact_force_event.collider2);
+        let Some(contact_pair) = rapier_context.contact_pair(entity_a, entity_b) else {
+            continue;
+        };
+        // Contact points are local to the first collider of the pair, which carries no scale of its own
+        let Some(point) = contact_pair
+            .find_deepest_contact()
+            .zip(transforms.get(contact_pair.collider1()).ok())
+            .map(|((_, contact), transform)| {
+                let (_, rotation, translation) = transform.to_scale_rotation_translation();
+                translation + rotation * contact.local_p1()
+            })
+        else {
+            continue;
+        };
+        let impulse =
+            contact_force_event.total_force_magnitude * rapier_context.integration_parameters.dt;
+        for (entity, other) in [(entity_a, entity_b), (entity_b, entity_a)] {
+            if vessels.contains(entity) {
+                vessel_collisions.send(VesselCollisionEvent {
+                    vessel: entity,
+                    other,
+                    impulse,
+                    point,
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,599 @@
+use std::path::{Path, PathBuf};
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::vessels::{
+    formations::FormationShape, movements::MovementType, targeting::TargetingType,
+};
+
+/// Where the action map is read from at startup and written to after rebinding
+const ACTION_MAP_PATH: &str = "config/action_map.ron";
+
+/// Something the player can do, independent of the input that triggers it
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum Action {
+    Move(MovementType),
+    /// Pull the trigger of a weapon group, indexed like the vessel's `weapon_groups`
+    FireGroup(usize),
+    Target(TargetingType),
+    CycleCamera,
+    /// Take control of the next or previous vessel of the player's fleet
+    NextVessel,
+    PreviousVessel,
+    /// Switch between flying the controlled vessel and commanding the fleet
+    ToggleCommandMode,
+    /// Click or drag a box to select vessels in command mode
+    Select,
+    /// Held to add to the selection instead of replacing it
+    AddToSelection,
+    /// Order the selection to move to, attack or follow what is under the cursor
+    Order,
+    /// Held while ordering onto a vessel to approach, orbit or match velocity with it instead
+    Approach,
+    Orbit,
+    MatchVelocity,
+    HoldPosition,
+    /// Form the selection up behind the controlled vessel, or the lowest numbered selected one
+    Formation(FormationShape),
+    BreakFormation,
+    /// Rebind the next action used to the input pressed after it
+    Rebind,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    /// A button on any connected gamepad
+    Gamepad(GamepadButtonType),
+}
+
+/// A gamepad or joystick axis giving proportional movement commands
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AxisBinding {
+    pub axis: GamepadAxisType,
+    /// Movement commanded by deflecting the axis towards positive values
+    pub positive: MovementType,
+    pub negative: MovementType,
+    /// Deflection below which the axis reads as centred, from 0 to 1
+    pub dead_zone: f32,
+    /// Response curve exponent, 1 is linear and larger values give finer control around the centre
+    pub exponent: f32,
+}
+impl AxisBinding {
+    /// Movement and its magnitude for a raw axis value, nothing inside the dead zone
+    fn movement(&self, value: f32) -> Option<(MovementType, f32)> {
+        if value.abs() <= self.dead_zone {
+            return None;
+        }
+        let deflection = ((value.abs() - self.dead_zone) / (1.0 - self.dead_zone)).min(1.0);
+        let magnitude = deflection.powf(self.exponent);
+        let movement_type = if value > 0.0 {
+            self.positive.clone()
+        } else {
+            self.negative.clone()
+        };
+        Some((movement_type, magnitude))
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ActionMapError {
+    #[error("could not access action map {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("could not parse action map {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: ron::error::SpannedError,
+    },
+    #[error("could not write action map {path}: {source}")]
+    Serialize { path: PathBuf, source: ron::Error },
+    #[error("invalid action map {path}, field `{field}`: {reason}")]
+    Invalid {
+        path: PathBuf,
+        field: String,
+        reason: String,
+    },
+}
+
+/// Inputs bound to every action, in the order they are listed in the config file
+#[derive(Resource, Clone, Serialize, Deserialize)]
+pub struct ActionMap {
+    pub bindings: Vec<(Action, Vec<Binding>)>,
+    #[serde(default)]
+    pub axes: Vec<AxisBinding>,
+}
+impl Default for ActionMap {
+    fn default() -> Self {
+        use Binding::*;
+        ActionMap {
+            bindings: vec![
+                (
+                    Action::Move(MovementType::Forward),
+                    vec![
+                        Key(KeyCode::KeyW),
+                        Gamepad(GamepadButtonType::RightTrigger2),
+                    ],
+                ),
+                (
+                    Action::Move(MovementType::Backward),
+                    vec![Key(KeyCode::KeyS), Gamepad(GamepadButtonType::LeftTrigger2)],
+                ),
+                (
+                    Action::Move(MovementType::StrafeUp),
+                    vec![Key(KeyCode::KeyR)],
+                ),
+                (
+                    Action::Move(MovementType::StrafeDown),
+                    vec![Key(KeyCode::KeyF)],
+                ),
+                (
+                    Action::Move(MovementType::StrafeLeft),
+                    vec![Key(KeyCode::KeyZ)],
+                ),
+                (
+                    Action::Move(MovementType::StrafeRight),
+                    vec![Key(KeyCode::KeyC)],
+                ),
+                (
+                    Action::Move(MovementType::TurnLeft),
+                    vec![Key(KeyCode::KeyA), Gamepad(GamepadButtonType::DPadLeft)],
+                ),
+                (
+                    Action::Move(MovementType::TurnRight),
+                    vec![Key(KeyCode::KeyD), Gamepad(GamepadButtonType::DPadRight)],
+                ),
+                (
+                    Action::Move(MovementType::PitchUp),
+                    vec![
+                        Key(KeyCode::ArrowDown),
+                        Gamepad(GamepadButtonType::DPadDown),
+                    ],
+                ),
+                (
+                    Action::Move(MovementType::PitchDown),
+                    vec![Key(KeyCode::ArrowUp), Gamepad(GamepadButtonType::DPadUp)],
+                ),
+                (
+                    Action::Move(MovementType::RollLeft),
+                    vec![Key(KeyCode::KeyQ), Gamepad(GamepadButtonType::LeftTrigger)],
+                ),
+                (
+                    Action::Move(MovementType::RollRight),
+                    vec![Key(KeyCode::KeyE), Gamepad(GamepadButtonType::RightTrigger)],
+                ),
+                (
+                    Action::Move(MovementType::ToggleFlightAssist),
+                    vec![Key(KeyCode::KeyV), Gamepad(GamepadButtonType::Select)],
+                ),
+                (
+                    Action::FireGroup(0),
+                    vec![
+                        Key(KeyCode::Space),
+                        Mouse(MouseButton::Left),
+                        Gamepad(GamepadButtonType::South),
+                    ],
+                ),
+                (
+                    Action::FireGroup(1),
+                    vec![
+                        Key(KeyCode::KeyM),
+                        Mouse(MouseButton::Right),
+                        Gamepad(GamepadButtonType::West),
+                    ],
+                ),
+                (
+                    Action::FireGroup(2),
+                    vec![Key(KeyCode::KeyN), Gamepad(GamepadButtonType::East)],
+                ),
+                (Action::FireGroup(3), vec![Key(KeyCode::KeyB)]),
+                (Action::FireGroup(4), vec![Key(KeyCode::KeyL)]),
+                (
+                    Action::Target(TargetingType::Nearest),
+                    vec![Key(KeyCode::KeyT), Gamepad(GamepadButtonType::North)],
+                ),
+                (Action::Target(TargetingType::Next), vec![Key(KeyCode::Tab)]),
+                (
+                    Action::Target(TargetingType::Clear),
+                    vec![Key(KeyCode::Backspace)],
+                ),
+                (
+                    Action::CycleCamera,
+                    vec![Key(KeyCode::F2), Gamepad(GamepadButtonType::Start)],
+                ),
+                (
+                    Action::NextVessel,
+                    vec![
+                        Key(KeyCode::BracketRight),
+                        Gamepad(GamepadButtonType::RightThumb),
+                    ],
+                ),
+                (
+                    Action::PreviousVessel,
+                    vec![
+                        Key(KeyCode::BracketLeft),
+                        Gamepad(GamepadButtonType::LeftThumb),
+                    ],
+                ),
+                (Action::ToggleCommandMode, vec![Key(KeyCode::F1)]),
+                (Action::Select, vec![Mouse(MouseButton::Left)]),
+                (
+                    Action::AddToSelection,
+                    vec![Key(KeyCode::ShiftLeft), Key(KeyCode::ShiftRight)],
+                ),
+                (Action::Order, vec![Mouse(MouseButton::Right)]),
+                (
+                    Action::Approach,
+                    vec![Key(KeyCode::ControlLeft), Key(KeyCode::ControlRight)],
+                ),
+                (
+                    Action::Orbit,
+                    vec![Key(KeyCode::AltLeft), Key(KeyCode::AltRight)],
+                ),
+                (Action::MatchVelocity, vec![Key(KeyCode::KeyG)]),
+                (Action::HoldPosition, vec![Key(KeyCode::KeyH)]),
+                (
+                    Action::Formation(FormationShape::LineAbreast),
+                    vec![Key(KeyCode::F5)],
+                ),
+                (
+                    Action::Formation(FormationShape::Wedge),
+                    vec![Key(KeyCode::F6)],
+                ),
+                (
+                    Action::Formation(FormationShape::Column),
+                    vec![Key(KeyCode::F7)],
+                ),
+                (
+                    Action::Formation(FormationShape::Sphere),
+                    vec![Key(KeyCode::F8)],
+                ),
+                (Action::BreakFormation, vec![Key(KeyCode::F9)]),
+                (Action::Rebind, vec![Key(KeyCode::F10)]),
+            ],
+            axes: vec![
+                AxisBinding {
+                    axis: GamepadAxisType::LeftStickX,
+                    positive: MovementType::TurnRight,
+                    negative: MovementType::TurnLeft,
+                    dead_zone: 0.1,
+                    exponent: 2.0,
+                },
+                AxisBinding {
+                    axis: GamepadAxisType::LeftStickY,
+                    positive: MovementType::PitchDown,
+                    negative: MovementType::PitchUp,
+                    dead_zone: 0.1,
+                    exponent: 2.0,
+                },
+                AxisBinding {
+                    axis: GamepadAxisType::RightStickX,
+                    positive: MovementType::RollRight,
+                    negative: MovementType::RollLeft,
+                    dead_zone: 0.1,
+                    exponent: 1.5,
+                },
+                AxisBinding {
+                    axis: GamepadAxisType::RightStickY,
+                    positive: MovementType::StrafeUp,
+                    negative: MovementType::StrafeDown,
+                    dead_zone: 0.15,
+                    exponent: 1.0,
+                },
+            ],
+        }
+    }
+}
+impl ActionMap {
+    pub fn load(path: &Path) -> Result<Self, ActionMapError> {
+        let bytes = std::fs::read(path).map_err(|source| ActionMapError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let action_map: ActionMap =
+            ron::de::from_bytes(&bytes).map_err(|source| ActionMapError::Parse {
+                path: path.to_path_buf(),
+                source,
+            })?;
+        for (index, axis_binding) in action_map.axes.iter().enumerate() {
+            let invalid = |field: &str, reason: &str| ActionMapError::Invalid {
+                path: path.to_path_buf(),
+                field: format!("axes[{}].{}", index, field),
+                reason: reason.to_owned(),
+            };
+            if !(0.0..1.0).contains(&axis_binding.dead_zone) {
+                return Err(invalid("dead_zone", "must be at least 0 and below 1"));
+            }
+            if !(axis_binding.exponent.is_finite() && axis_binding.exponent > 0.0) {
+                return Err(invalid("exponent", "must be a positive number"));
+            }
+        }
+        Ok(action_map)
+    }
+    pub fn save(&self, path: &Path) -> Result<(), ActionMapError> {
+        let serialized = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|source| ActionMapError::Serialize {
+                path: path.to_path_buf(),
+                source,
+            })?;
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory).map_err(|source| ActionMapError::Io {
+                path: path.to_path_buf(),
+                source,
+            })?;
+        }
+        std::fs::write(path, serialized).map_err(|source| ActionMapError::Io {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+    /// Reads the player's action map, falling back to the default bindings if there is none yet
+    pub fn load_or_default() -> Self {
+        let path = Path::new(ACTION_MAP_PATH);
+        if !path.exists() {
+            return ActionMap::default();
+        }
+        ActionMap::load(path).unwrap_or_else(|error| {
+            warn!("{}, using the default bindings", error);
+            ActionMap::default()
+        })
+    }
+    /// Replaces the input of `action` on the device `binding` belongs to and takes it away from every other action
+    pub fn rebind(&mut self, action: &Action, binding: Binding) {
+        for (bound_action, bindings) in self.bindings.iter_mut() {
+            if bound_action == action {
+                bindings.retain(|bound| {
+                    std::mem::discriminant(bound) != std::mem::discriminant(&binding)
+                });
+                bindings.push(binding);
+            } else {
+                bindings.retain(|bound| *bound != binding);
+            }
+        }
+        if !self
+            .bindings
+            .iter()
+            .any(|(bound_action, _)| bound_action == action)
+        {
+            self.bindings.push((action.clone(), vec![binding]));
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum ButtonState {
+    Pressed,
+    JustPressed,
+    JustReleased,
+}
+
+/// Reads the state of actions from the inputs they are bound to
+#[derive(SystemParam)]
+pub struct ActionInput<'w> {
+    action_map: Res<'w, ActionMap>,
+    keys: Res<'w, ButtonInput<KeyCode>>,
+    mouse_buttons: Res<'w, ButtonInput<MouseButton>>,
+    gamepad_buttons: Res<'w, ButtonInput<GamepadButton>>,
+    gamepad_axes: Res<'w, Axis<GamepadAxis>>,
+    gamepads: Res<'w, Gamepads>,
+}
+impl<'w> ActionInput<'w> {
+    fn binding_state(&self, binding: &Binding, state: ButtonState) -> bool {
+        fn check<T: Copy + Eq + std::hash::Hash + Send + Sync>(
+            input: &ButtonInput<T>,
+            button: T,
+            state: ButtonState,
+        ) -> bool {
+            match state {
+                ButtonState::Pressed => input.pressed(button),
+                ButtonState::JustPressed => input.just_pressed(button),
+                ButtonState::JustReleased => input.just_released(button),
+            }
+        }
+        match binding {
+            Binding::Key(key) => check(&self.keys, *key, state),
+            Binding::Mouse(button) => check(&self.mouse_buttons, *button, state),
+            Binding::Gamepad(button_type) => self.gamepads.iter().any(|gamepad| {
+                check(
+                    &self.gamepad_buttons,
+                    GamepadButton::new(gamepad, *button_type),
+                    state,
+                )
+            }),
+        }
+    }
+    fn actions(&self, state: ButtonState) -> impl Iterator<Item = &Action> {
+        self.action_map
+            .bindings
+            .iter()
+            .filter(move |(_, bindings)| {
+                bindings
+                    .iter()
+                    .any(|binding| self.binding_state(binding, state))
+            })
+            .map(|(action, _)| action)
+    }
+    /// Actions with at least one of their inputs held down
+    pub fn pressed(&self) -> impl Iterator<Item = &Action> {
+        self.actions(ButtonState::Pressed)
+    }
+    /// Actions with at least one of their inputs pressed this frame
+    pub fn just_pressed(&self) -> impl Iterator<Item = &Action> {
+        self.actions(ButtonState::JustPressed)
+    }
+    /// Actions with at least one of their inputs let go this frame
+    pub fn just_released(&self) -> impl Iterator<Item = &Action> {
+        self.actions(ButtonState::JustReleased)
+    }
+    /// Proportional movement commands from the bound axes of every connected gamepad
+    pub fn axis_movements(&self) -> Vec<(MovementType, f32)> {
+        self.action_map
+            .axes
+            .iter()
+            .flat_map(|axis_binding| {
+                self.gamepads.iter().filter_map(move |gamepad| {
+                    self.gamepad_axes
+                        .get(GamepadAxis::new(gamepad, axis_binding.axis))
+                        .and_then(|value| axis_binding.movement(value))
+                })
+            })
+            .collect()
+    }
+}
+
+/// Waits for the next input and binds it to `action`, escape cancels
+#[derive(Event)]
+pub struct RebindAction {
+    pub action: Action,
+}
+
+#[derive(Resource, Default)]
+struct PendingRebind(Option<Action>);
+
+/// Whether the rebind action was used and the action to rebind is awaited
+#[derive(Resource, Default)]
+struct ChoosingRebind(bool);
+
+pub struct BindingsPlugin;
+impl Plugin for BindingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ActionMap::load_or_default())
+            .init_resource::<PendingRebind>()
+            .init_resource::<ChoosingRebind>()
+            .add_event::<RebindAction>()
+            // The input that chose the action to rebind must not be captured as its new binding
+            .add_systems(
+                Update,
+                (choose_rebinding, capture_rebinding, start_rebinding).chain(),
+            );
+    }
+}
+
+/// After the rebind action, rebinds the next action used, escape cancels
+fn choose_rebinding(
+    action_input: ActionInput,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut choosing_rebind: ResMut<ChoosingRebind>,
+    mut rebind_events: EventWriter<RebindAction>,
+) {
+    if action_input
+        .just_pressed()
+        .any(|action| *action == Action::Rebind)
+    {
+        info!("Use the action to rebind");
+        choosing_rebind.0 = true;
+        return;
+    }
+    if !choosing_rebind.0 {
+        return;
+    }
+    if keys.just_pressed(KeyCode::Escape) {
+        choosing_rebind.0 = false;
+        return;
+    }
+    if let Some(action) = action_input.just_pressed().next() {
+        rebind_events.send(RebindAction {
+            action: action.clone(),
+        });
+        choosing_rebind.0 = false;
+    }
+}
+
+fn start_rebinding(
+    mut rebind_events: EventReader<RebindAction>,
+    mut pending_rebind: ResMut<PendingRebind>,
+) {
+    if let Some(rebind_event) = rebind_events.read().last() {
+        info!("Press the new input for {:?}", rebind_event.action);
+        pending_rebind.0 = Some(rebind_event.action.clone());
+    }
+}
+
+/// Binds the first input pressed while a rebind is pending and saves the action map
+fn capture_rebinding(
+    mut pending_rebind: ResMut<PendingRebind>,
+    mut action_map: ResMut<ActionMap>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+) {
+    let Some(action) = pending_rebind.0.clone() else {
+        return;
+    };
+    if keys.just_pressed(KeyCode::Escape) {
+        pending_rebind.0 = None;
+        return;
+    }
+    let Some(binding) = keys
+        .get_just_pressed()
+        .next()
+        .map(|key| Binding::Key(*key))
+        .or_else(|| {
+            mouse_buttons
+                .get_just_pressed()
+                .next()
+                .map(|button| Binding::Mouse(*button))
+        })
+        .or_else(|| {
+            gamepad_buttons
+                .get_just_pressed()
+                .next()
+                .map(|button| Binding::Gamepad(button.button_type))
+        })
+    else {
+        return;
+    };
+    action_map.rebind(&action, binding);
+    pending_rebind.0 = None;
+    if let Err(error) = action_map.save(Path::new(ACTION_MAP_PATH)) {
+        warn!("{}", error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn axis_binding(dead_zone: f32, exponent: f32) -> AxisBinding {
+        AxisBinding {
+            axis: GamepadAxisType::LeftStickX,
+            positive: MovementType::TurnRight,
+            negative: MovementType::TurnLeft,
+            dead_zone,
+            exponent,
+        }
+    }
+
+    #[test]
+    fn axis_movement_is_nothing_inside_dead_zone() {
+        let axis_binding = axis_binding(0.2, 1.0);
+        assert_eq!(axis_binding.movement(0.0), None);
+        assert_eq!(axis_binding.movement(0.2), None);
+        assert_eq!(axis_binding.movement(-0.15), None);
+    }
+
+    #[test]
+    fn axis_movement_rescales_past_dead_zone() {
+        let axis_binding = axis_binding(0.2, 1.0);
+        let (movement_type, magnitude) = axis_binding.movement(0.6).unwrap();
+        assert_eq!(movement_type, MovementType::TurnRight);
+        assert!((magnitude - 0.5).abs() < 1e-6);
+        let (movement_type, magnitude) = axis_binding.movement(-1.0).unwrap();
+        assert_eq!(movement_type, MovementType::TurnLeft);
+        assert!((magnitude - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn axis_movement_follows_response_curve() {
+        let axis_binding = axis_binding(0.0, 2.0);
+        let (_, magnitude) = axis_binding.movement(0.5).unwrap();
+        assert!((magnitude - 0.25).abs() < 1e-6);
+        let (_, magnitude) = axis_binding.movement(-1.0).unwrap();
+        assert!((magnitude - 1.0).abs() < 1e-6);
+    }
+}
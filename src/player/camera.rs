@@ -1,5 +1,3 @@
-use std::f32::consts::PI;
-
 use bevy::{
     core_pipeline::{bloom::BloomSettings, tonemapping::Tonemapping},
     prelude::*,
@@ -7,14 +5,27 @@ use bevy::{
 };
 
 use crate::vessels::{
-    movements::{apply_velocity, VelocityVector},
+    movements::{read_velocity, VelocityVector},
     vessels::VesselID,
 };
 
-use super::player::Player;
+use super::{
+    command::{InputMode, Selected},
+    control::ControlledVessel,
+};
+
+/// Height above the vessel of the top-down camera
+const TOP_DOWN_ALTITUDE: f32 = 400.0;
+/// Height above the fleet of the tactical camera in command mode
+const TACTICAL_ALTITUDE: f32 = 500.0;
 enum CameraRotation {
     MatchY,
+    /// Looks down on the vessel from above, north up
+    TopDown,
 }
+/// Switches the camera to its next [`CameraRotation`]
+#[derive(Event)]
+pub struct CycleCameraEvent;
 #[derive(Component)]
 struct CameraBehaviour {
     offset: f32,
@@ -25,8 +36,9 @@ struct CameraBehaviour {
 pub struct FlightCameraPlugin;
 impl Plugin for FlightCameraPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, enable_camera)
-            .add_systems(Update, track_camera.after(apply_velocity));
+        app.add_event::<CycleCameraEvent>()
+            .add_systems(Startup, enable_camera)
+            .add_systems(Update, (cycle_camera, track_camera.after(read_velocity)));
     }
 }
 pub fn enable_camera(mut commands: Commands) {
@@ -95,15 +107,13 @@ fn calculate_focussing_point(
             .with_rotation(vessel_transform.rotation)
             .translation;
     let mut rotated_focussing_point = vessel_transform.clone();
-    if vessel_velocity.angular_velocity.length() == 0.0 {
+    if vessel_velocity.angular_velocity.length() == 0.0 || vessel_velocity.turn_radius == 0.0 {
         return focussing_point;
     }
-    let turn_circle_circumference = -2.0 * PI * vessel_velocity.turn_radius;
-    let mut turn_circle_center = vessel_transform.clone();
-    turn_circle_center.translation.z += vessel_velocity.turn_radius;
+    let turn_axis = (vessel_transform.rotation * vessel_velocity.angular_velocity).normalize();
     rotated_focussing_point.rotate_around(
-        turn_circle_center.translation,
-        Quat::from_rotation_y(2.0 * PI * (focussing_distance / turn_circle_circumference)),
+        vessel_velocity.turn_circle_center,
+        Quat::from_axis_angle(turn_axis, focussing_distance / vessel_velocity.turn_radius),
     );
 
     let rotation_interpolation_factor = logistic_interpolation(
@@ -128,29 +138,80 @@ fn calculate_camera_position(
     return focal_point + (*focussing_point - focal_point).normalize() * camera_behaviour.offset;
 }
 
+fn cycle_camera(
+    mut cycle_camera_events: EventReader<CycleCameraEvent>,
+    mut cameras: Query<&mut CameraBehaviour>,
+) {
+    for _ in cycle_camera_events.read() {
+        for mut camera_behaviour in cameras.iter_mut() {
+            camera_behaviour.camera_rotation = match camera_behaviour.camera_rotation {
+                CameraRotation::MatchY => CameraRotation::TopDown,
+                CameraRotation::TopDown => CameraRotation::MatchY,
+            };
+        }
+    }
+}
+
 fn track_camera(
+    controlled_vessel: Res<ControlledVessel>,
+    input_mode: Res<InputMode>,
     controlled_vessels: Query<(&Transform, &VesselID, &VelocityVector)>,
+    selected: Query<&Transform, (With<Selected>, With<VesselID>)>,
     mut cameras: Query<(&mut Transform, &CameraBehaviour), Without<VesselID>>,
 ) {
     let Ok((mut camera_transform, camera_behaviour)) = cameras.get_single_mut() else {
         return;
     };
+    if *input_mode == InputMode::Command {
+        // Overlook the selection, or the controlled vessel while nothing is selected
+        let centre = if selected.is_empty() {
+            controlled_vessels
+                .iter()
+                .find(|(_, vessel_id, _)| **vessel_id == controlled_vessel.vessel_id)
+                .map(|(vessel_transform, _, _)| vessel_transform.translation)
+        } else {
+            Some(
+                selected
+                    .iter()
+                    .map(|vessel_transform| vessel_transform.translation)
+                    .sum::<Vec3>()
+                    / selected.iter().len() as f32,
+            )
+        };
+        if let Some(centre) = centre {
+            *camera_transform = Transform::from_translation(centre + Vec3::Y * TACTICAL_ALTITUDE)
+                .looking_at(centre, Vec3::X);
+        }
+        return;
+    }
     for (vessel_transform, vessel_id, velocity_vector) in controlled_vessels.iter() {
-        if vessel_id.player == Player::Host {
-            let focussing_point: Vec3 =
-                calculate_focussing_point(&velocity_vector, &camera_behaviour, &vessel_transform);
-            let mut new_camera_transform = camera_transform.clone();
-            new_camera_transform.translation =
-                calculate_camera_position(&vessel_transform, &camera_behaviour, &focussing_point);
+        if *vessel_id == controlled_vessel.vessel_id {
             match camera_behaviour.camera_rotation {
                 CameraRotation::MatchY => {
+                    let focussing_point: Vec3 = calculate_focussing_point(
+                        &velocity_vector,
+                        &camera_behaviour,
+                        &vessel_transform,
+                    );
+                    let mut new_camera_transform = camera_transform.clone();
+                    new_camera_transform.translation = calculate_camera_position(
+                        &vessel_transform,
+                        &camera_behaviour,
+                        &focussing_point,
+                    );
                     new_camera_transform.look_at(focussing_point, Vec3::Y);
+                    new_camera_transform
+                        .rotate_around(vessel_transform.translation, vessel_transform.rotation);
+
+                    *camera_transform = new_camera_transform;
+                }
+                CameraRotation::TopDown => {
+                    *camera_transform = Transform::from_translation(
+                        vessel_transform.translation + Vec3::Y * TOP_DOWN_ALTITUDE,
+                    )
+                    .looking_at(vessel_transform.translation, Vec3::X);
                 }
             };
-            new_camera_transform
-                .rotate_around(vessel_transform.translation, vessel_transform.rotation);
-
-            *camera_transform = new_camera_transform;
         }
     }
 }
@@ -0,0 +1,392 @@
+use bevy::{
+    color::palettes::css::LIME, ecs::system::SystemParam, prelude::*, window::PrimaryWindow,
+};
+use bevy_rapier3d::prelude::*;
+
+use crate::vessels::{
+    formations::{slot_spacing, FormationEvent, FormationShape, FormationType, Wingman},
+    orders::{Order, OrderEvent, OrderQueue},
+    vessels::{VesselDefinition, VesselID},
+};
+
+use super::{
+    bindings::{Action, ActionInput},
+    control::{ControlledVessel, SwitchControlEvent, SwitchControlType},
+};
+
+/// Cursor travel in pixels below which a selection counts as a click rather than a box
+const CLICK_TOLERANCE: f32 = 4.0;
+/// Farthest a click can pick a vessel
+const PICK_DISTANCE: f32 = 10000.0;
+/// Seconds within which a second click on the same vessel makes a double-click
+const DOUBLE_CLICK_TIME: f32 = 0.4;
+
+/// Whether the player is flying the controlled vessel or commanding the fleet
+#[derive(Resource, Default, PartialEq, Eq)]
+pub enum InputMode {
+    #[default]
+    Flight,
+    Command,
+}
+
+/// Marks the vessels the player's next order goes to
+#[derive(Component)]
+pub struct Selected;
+
+/// Screen position the selection box was started at
+#[derive(Resource, Default)]
+struct SelectionStart(Option<Vec2>);
+
+/// A selected vessel orders are issued to
+type OrderedVessel<'a> = (Entity, &'a VesselID, &'a Transform, &'a VesselDefinition);
+
+pub struct CommandPlugin;
+impl Plugin for CommandPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InputMode>()
+            .init_resource::<SelectionStart>()
+            .add_systems(
+                Update,
+                (
+                    toggle_command_mode,
+                    (
+                        select_vessels,
+                        take_helm,
+                        issue_orders,
+                        form_formation,
+                        draw_selection,
+                    )
+                        .chain()
+                        .run_if(resource_equals(InputMode::Command)),
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn toggle_command_mode(
+    mut commands: Commands,
+    action_input: ActionInput,
+    mut input_mode: ResMut<InputMode>,
+    mut selection_start: ResMut<SelectionStart>,
+    selected: Query<Entity, With<Selected>>,
+) {
+    if !action_input
+        .just_pressed()
+        .any(|action| *action == Action::ToggleCommandMode)
+    {
+        return;
+    }
+    *input_mode = match *input_mode {
+        InputMode::Flight => InputMode::Command,
+        InputMode::Command => {
+            for entity in selected.iter() {
+                commands.entity(entity).remove::<Selected>();
+            }
+            selection_start.0 = None;
+            InputMode::Flight
+        }
+    };
+}
+
+/// Relates the cursor on the primary window to the world seen by the camera
+#[derive(SystemParam)]
+struct Cursor<'w, 's> {
+    windows: Query<'w, 's, &'static Window, With<PrimaryWindow>>,
+    cameras: Query<'w, 's, (&'static Camera, &'static GlobalTransform)>,
+    rapier_context: Res<'w, RapierContext>,
+}
+impl<'w, 's> Cursor<'w, 's> {
+    /// Cursor position and the ray from the camera through it
+    fn ray(&self) -> Option<(Vec2, Ray3d)> {
+        let cursor = self.windows.get_single().ok()?.cursor_position()?;
+        let (camera, camera_transform) = self.cameras.get_single().ok()?;
+        let ray = camera.viewport_to_world(camera_transform, cursor)?;
+        Some((cursor, ray))
+    }
+    /// The first solid body under the cursor
+    fn pick(&self) -> Option<Entity> {
+        let (_, ray) = self.ray()?;
+        self.rapier_context
+            .cast_ray(
+                ray.origin,
+                *ray.direction,
+                PICK_DISTANCE,
+                true,
+                QueryFilter::default().exclude_sensors(),
+            )
+            .map(|(entity, _)| entity)
+    }
+    fn world_to_viewport(&self, position: Vec3) -> Option<Vec2> {
+        let (camera, camera_transform) = self.cameras.get_single().ok()?;
+        camera.world_to_viewport(camera_transform, position)
+    }
+}
+
+/// Clicking selects the owned vessel under the cursor, dragging selects every owned vessel in the box
+fn select_vessels(
+    mut commands: Commands,
+    action_input: ActionInput,
+    controlled_vessel: Res<ControlledVessel>,
+    mut selection_start: ResMut<SelectionStart>,
+    cursor: Cursor,
+    vessels: Query<(Entity, &VesselID, &GlobalTransform, Has<Selected>)>,
+) {
+    let Some((cursor_position, _)) = cursor.ray() else {
+        return;
+    };
+    if action_input
+        .just_pressed()
+        .any(|action| *action == Action::Select)
+    {
+        selection_start.0 = Some(cursor_position);
+    }
+    if !action_input
+        .just_released()
+        .any(|action| *action == Action::Select)
+    {
+        return;
+    }
+    let Some(start) = selection_start.0.take() else {
+        return;
+    };
+    if !action_input
+        .pressed()
+        .any(|action| *action == Action::AddToSelection)
+    {
+        for (entity, _, _, _) in vessels.iter().filter(|(_, _, _, selected)| *selected) {
+            commands.entity(entity).remove::<Selected>();
+        }
+    }
+    let owned = |vessel_id: &VesselID| vessel_id.player == controlled_vessel.vessel_id.player;
+
+    if start.distance(cursor_position) < CLICK_TOLERANCE {
+        let Some(hit_entity) = cursor.pick() else {
+            return;
+        };
+        if let Ok((entity, vessel_id, _, _)) = vessels.get(hit_entity) {
+            if owned(vessel_id) {
+                commands.entity(entity).insert(Selected);
+            }
+        }
+        return;
+    }
+    let selection_box = Rect::from_corners(start, cursor_position);
+    for (entity, vessel_id, vessel_transform, _) in vessels.iter() {
+        let on_screen = cursor.world_to_viewport(vessel_transform.translation());
+        if owned(vessel_id) && on_screen.is_some_and(|position| selection_box.contains(position)) {
+            commands.entity(entity).insert(Selected);
+        }
+    }
+}
+
+/// Double-clicking an owned vessel takes its helm
+fn take_helm(
+    time: Res<Time>,
+    action_input: ActionInput,
+    cursor: Cursor,
+    vessels: Query<&VesselID>,
+    mut last_click: Local<Option<(Entity, f32)>>,
+    mut switch_control_events: EventWriter<SwitchControlEvent>,
+) {
+    if !action_input
+        .just_pressed()
+        .any(|action| *action == Action::Select)
+    {
+        return;
+    }
+    let Some(clicked) = cursor.pick() else {
+        *last_click = None;
+        return;
+    };
+    let now = time.elapsed_seconds();
+    let double_click = last_click.is_some_and(|(last_clicked, clicked_at)| {
+        last_clicked == clicked && now - clicked_at <= DOUBLE_CLICK_TIME
+    });
+    if !double_click {
+        *last_click = Some((clicked, now));
+        return;
+    }
+    *last_click = None;
+    if let Ok(vessel_id) = vessels.get(clicked) {
+        switch_control_events.send(SwitchControlEvent {
+            switch_type: SwitchControlType::To(vessel_id.clone()),
+        });
+    }
+}
+
+/// Orders the selection to attack a hostile vessel, follow an owned one or move to the point under the cursor.
+/// With a modifier held the selection approaches, orbits or matches velocity with the vessel instead.
+fn issue_orders(
+    action_input: ActionInput,
+    controlled_vessel: Res<ControlledVessel>,
+    cursor: Cursor,
+    vessels: Query<(&VesselID, &Transform)>,
+    // Wingmen follow their formation's leader rather than orders of their own
+    selected: Query<OrderedVessel, (With<Selected>, Without<Wingman>)>,
+    mut order_events: EventWriter<OrderEvent>,
+) {
+    let mut ordered: Vec<OrderedVessel> = selected.iter().collect();
+    if ordered.is_empty() {
+        return;
+    }
+    ordered.sort_by_key(|(_, vessel_id, _, _)| vessel_id.id);
+    // Shift queues the order behind the ones already given
+    let queue = action_input
+        .pressed()
+        .any(|action| *action == Action::AddToSelection);
+    if action_input
+        .just_pressed()
+        .any(|action| *action == Action::HoldPosition)
+    {
+        for (_, vessel_id, _, _) in ordered.iter() {
+            order_events.send(OrderEvent {
+                vessel_id: (*vessel_id).clone(),
+                order: Order::Hold,
+                queue,
+            });
+        }
+        return;
+    }
+    if !action_input
+        .just_pressed()
+        .any(|action| *action == Action::Order)
+    {
+        return;
+    }
+    let Some((_, ray)) = cursor.ray() else {
+        return;
+    };
+    let hit_vessel = cursor.pick().and_then(|hit_entity| {
+        vessels
+            .get(hit_entity)
+            .ok()
+            .map(|(vessel_id, transform)| (hit_entity, vessel_id, transform))
+    });
+    let centre = ordered
+        .iter()
+        .map(|(_, _, transform, _)| transform.translation)
+        .sum::<Vec3>()
+        / ordered.len() as f32;
+    let spacing = slot_spacing(
+        ordered
+            .iter()
+            .map(|(_, _, _, vessel_definition)| *vessel_definition),
+    );
+    let modifier = action_input.pressed().find(|action| {
+        matches!(
+            action,
+            Action::Approach | Action::Orbit | Action::MatchVelocity
+        )
+    });
+    let order = match (hit_vessel, modifier) {
+        (Some((hit_entity, _, _)), Some(Action::Approach)) => Order::Approach {
+            target: hit_entity,
+            distance: spacing,
+        },
+        // The selection circles at the range it is ordered from
+        (Some((hit_entity, _, hit_transform)), Some(Action::Orbit)) => Order::Orbit {
+            target: hit_entity,
+            radius: centre.distance(hit_transform.translation).max(spacing),
+        },
+        (Some((hit_entity, _, _)), Some(Action::MatchVelocity)) => Order::MatchVelocity(hit_entity),
+        (Some((hit_entity, vessel_id, _)), _)
+            if vessel_id.player == controlled_vessel.vessel_id.player =>
+        {
+            Order::Follow(hit_entity)
+        }
+        (Some((hit_entity, _, _)), _) => Order::Attack(hit_entity),
+        (None, _) => {
+            // Move orders land on the plane the selection is flying in
+            let Some(distance) =
+                ray.intersect_plane(Vec3::Y * centre.y, InfinitePlane3d::new(Vec3::Y))
+            else {
+                return;
+            };
+            let destination = ray.get_point(distance);
+            // Spread the destinations line abreast across the direction of travel so the vessels
+            // don't all arrive at the same point
+            let heading = Quat::from_rotation_arc(
+                Vec3::X,
+                (destination - centre)
+                    .with_y(0.0)
+                    .try_normalize()
+                    .unwrap_or(Vec3::X),
+            );
+            for (slot, (_, vessel_id, _, _)) in ordered.iter().enumerate() {
+                let offset = FormationShape::LineAbreast.slot_offset(slot, ordered.len(), spacing);
+                order_events.send(OrderEvent {
+                    vessel_id: (*vessel_id).clone(),
+                    order: Order::MoveTo(destination + heading * offset),
+                    queue,
+                });
+            }
+            return;
+        }
+    };
+    for (entity, vessel_id, _, _) in ordered.iter() {
+        if order.target() == Some(*entity) {
+            continue;
+        }
+        order_events.send(OrderEvent {
+            vessel_id: (*vessel_id).clone(),
+            order: order.clone(),
+            queue,
+        });
+    }
+}
+
+/// Forms the selection up in the chosen shape, or takes it out of formation
+fn form_formation(
+    action_input: ActionInput,
+    controlled_vessel: Res<ControlledVessel>,
+    selected: Query<(Entity, &VesselID), With<Selected>>,
+    mut formation_events: EventWriter<FormationEvent>,
+) {
+    for action in action_input.just_pressed() {
+        let formation_type = match action {
+            Action::Formation(shape) => FormationType::Form(*shape),
+            Action::BreakFormation => FormationType::Break,
+            _ => continue,
+        };
+        // The controlled vessel leads, otherwise the selected vessel with the lowest id
+        let mut members: Vec<(Entity, &VesselID)> = selected.iter().collect();
+        members.sort_by_key(|(_, vessel_id)| {
+            (**vessel_id != controlled_vessel.vessel_id, vessel_id.id)
+        });
+        formation_events.send(FormationEvent {
+            formation_type,
+            members: members.into_iter().map(|(entity, _)| entity).collect(),
+        });
+    }
+}
+
+/// Rings the selected vessels and traces the points their queued orders lead through
+fn draw_selection(
+    mut gizmos: Gizmos,
+    selected: Query<(&Transform, &VesselDefinition, &OrderQueue), With<Selected>>,
+    targets: Query<&Transform, With<VesselID>>,
+) {
+    for (transform, vessel_definition, order_queue) in selected.iter() {
+        gizmos.circle(
+            transform.translation,
+            Dir3::Y,
+            vessel_definition.collider_half_extents.length() * 1.2,
+            LIME,
+        );
+        let waypoints = order_queue.0.iter().flat_map(|order| match order {
+            Order::MoveTo(destination) => vec![*destination],
+            Order::Patrol { waypoints, .. } => waypoints.clone(),
+            _ => order
+                .target()
+                .and_then(|target| targets.get(target).ok())
+                .map(|target_transform| target_transform.translation)
+                .into_iter()
+                .collect(),
+        });
+        gizmos.linestrip(
+            std::iter::once(transform.translation).chain(waypoints),
+            LIME,
+        );
+    }
+}
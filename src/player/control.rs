@@ -0,0 +1,126 @@
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use crate::vessels::{damage::VesselDestroyed, vessels::VesselID};
+
+use super::{command::InputMode, player::Player};
+
+/// The vessel the local player's input, camera and HUD are bound to
+#[derive(Resource)]
+pub struct ControlledVessel {
+    pub vessel_id: VesselID,
+}
+impl Default for ControlledVessel {
+    fn default() -> Self {
+        ControlledVessel {
+            vessel_id: VesselID {
+                player: Player::Host,
+                id: 0,
+            },
+        }
+    }
+}
+
+/// Tells which vessel the player flies by hand. Autopilot, formation keeping and collision avoidance leave
+/// it alone so they don't fight the player's own input.
+#[derive(SystemParam)]
+pub struct Helm<'w> {
+    controlled_vessel: Res<'w, ControlledVessel>,
+    input_mode: Res<'w, InputMode>,
+}
+impl<'w> Helm<'w> {
+    /// Whether `vessel_id` is the controlled vessel while the player is in flight mode
+    pub fn is_hand_flown(&self, vessel_id: &VesselID) -> bool {
+        *self.input_mode == InputMode::Flight && *vessel_id == self.controlled_vessel.vessel_id
+    }
+}
+
+pub enum SwitchControlType {
+    /// Take the helm of the owned vessel with the next higher id, wrapping around
+    Next,
+    Previous,
+    To(VesselID),
+}
+#[derive(Event)]
+pub struct SwitchControlEvent {
+    pub switch_type: SwitchControlType,
+}
+
+pub struct ControlPlugin;
+impl Plugin for ControlPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ControlledVessel>()
+            .add_event::<SwitchControlEvent>()
+            .add_systems(Update, (switch_control, take_over_on_destruction));
+    }
+}
+
+/// Ids of the vessels owned by the controlling player, sorted
+fn owned_vessel_ids<'a>(
+    controlled_vessel: &ControlledVessel,
+    vessels: impl Iterator<Item = &'a VesselID>,
+) -> Vec<u32> {
+    let mut owned: Vec<u32> = vessels
+        .filter(|vessel_id| vessel_id.player == controlled_vessel.vessel_id.player)
+        .map(|vessel_id| vessel_id.id)
+        .collect();
+    owned.sort_unstable();
+    owned
+}
+
+fn switch_control(
+    mut switch_control_events: EventReader<SwitchControlEvent>,
+    mut controlled_vessel: ResMut<ControlledVessel>,
+    vessels: Query<&VesselID>,
+) {
+    for switch_control_event in switch_control_events.read() {
+        let owned = owned_vessel_ids(&controlled_vessel, vessels.iter());
+        let current_index = owned
+            .iter()
+            .position(|id| *id == controlled_vessel.vessel_id.id);
+        let new_id = match &switch_control_event.switch_type {
+            SwitchControlType::Next => current_index
+                .map_or(owned.first(), |index| owned.get((index + 1) % owned.len()))
+                .copied(),
+            SwitchControlType::Previous => current_index
+                .map_or(owned.last(), |index| {
+                    owned.get((index + owned.len() - 1) % owned.len())
+                })
+                .copied(),
+            SwitchControlType::To(vessel_id) => (vessel_id.player
+                == controlled_vessel.vessel_id.player
+                && owned.contains(&vessel_id.id))
+            .then_some(vessel_id.id),
+        };
+        if let Some(new_id) = new_id {
+            controlled_vessel.vessel_id.id = new_id;
+        }
+    }
+}
+
+/// Hands control to the next surviving owned vessel when the controlled one is destroyed
+fn take_over_on_destruction(
+    mut vessel_destroyed: EventReader<VesselDestroyed>,
+    mut controlled_vessel: ResMut<ControlledVessel>,
+    vessels: Query<(Entity, &VesselID)>,
+) {
+    for destroyed in vessel_destroyed.read() {
+        if destroyed.vessel_id != controlled_vessel.vessel_id {
+            continue;
+        }
+        let survivors = owned_vessel_ids(
+            &controlled_vessel,
+            vessels
+                .iter()
+                .filter(|(entity, _)| *entity != destroyed.entity)
+                .map(|(_, vessel_id)| vessel_id),
+        );
+        let Some(new_id) = survivors
+            .iter()
+            .find(|id| **id > destroyed.vessel_id.id)
+            .or(survivors.first())
+        else {
+            continue;
+        };
+        controlled_vessel.vessel_id.id = *new_id;
+    }
+}
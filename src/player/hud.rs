@@ -0,0 +1,98 @@
+use bevy::{color::palettes::css::ORANGE, prelude::*};
+
+use crate::vessels::{
+    gunnery::LeadIndicator,
+    vessels::{VesselDefinition, VesselID},
+    weapons::Target,
+};
+
+use super::control::ControlledVessel;
+
+pub struct HudPlugin;
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_weapon_group_readout)
+            .add_systems(Update, (draw_lead_indicator, update_weapon_group_readout));
+    }
+}
+
+/// Lists the weapon groups of the controlled vessel with their fire modes
+#[derive(Component)]
+struct WeaponGroupReadout;
+
+fn spawn_weapon_group_readout(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 18.0,
+                color: ORANGE.into(),
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(12.0),
+            left: Val::Px(12.0),
+            ..default()
+        }),
+        WeaponGroupReadout,
+    ));
+}
+
+fn update_weapon_group_readout(
+    controlled_vessel: Res<ControlledVessel>,
+    vessels: Query<(&VesselID, &VesselDefinition)>,
+    mut readouts: Query<&mut Text, With<WeaponGroupReadout>>,
+) {
+    let Ok(mut readout) = readouts.get_single_mut() else {
+        return;
+    };
+    readout.sections[0].value = vessels
+        .iter()
+        .find(|(vessel_id, _)| **vessel_id == controlled_vessel.vessel_id)
+        .map(|(_, vessel_definition)| {
+            vessel_definition
+                .weapon_groups
+                .iter()
+                .map(|weapon_group| format!("{} ({:?})", weapon_group.name, weapon_group.fire_mode))
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .unwrap_or_default();
+}
+
+/// Marks where the player's primary weapons have to be pointed to hit the locked target
+fn draw_lead_indicator(
+    mut gizmos: Gizmos,
+    controlled_vessel: Res<ControlledVessel>,
+    vessels: Query<(&VesselID, &LeadIndicator, &Target)>,
+    targets: Query<&Transform, With<VesselID>>,
+    cameras: Query<&Transform, With<Camera3d>>,
+) {
+    let Ok(camera_transform) = cameras.get_single() else {
+        return;
+    };
+    for (vessel_id, lead_indicator, target) in vessels.iter() {
+        if *vessel_id != controlled_vessel.vessel_id {
+            continue;
+        }
+        let Ok(target_transform) = targets.get(target.0) else {
+            continue;
+        };
+        let distance = camera_transform
+            .translation
+            .distance(lead_indicator.position);
+        gizmos.circle(
+            lead_indicator.position,
+            camera_transform.back(),
+            distance * 0.01,
+            ORANGE,
+        );
+        gizmos.line(
+            target_transform.translation,
+            lead_indicator.position,
+            ORANGE,
+        );
+    }
+}
@@ -1,80 +1,120 @@
-use bevy::{color::palettes::css::GREEN, prelude::*};
+use bevy::prelude::*;
 
 use crate::vessels::{
     movements::{MovementEvent, MovementType},
-    vessels::VesselID,
-    weapons::{WeaponStats, WeaponsFireEvent, WeaponsType},
+    targeting::TargetingEvent,
+    weapon_groups::WeaponGroupFireEvent,
 };
 
-use super::player::Player;
+use super::{
+    bindings::{Action, ActionInput, BindingsPlugin},
+    camera::CycleCameraEvent,
+    command::{CommandPlugin, InputMode},
+    control::{ControlPlugin, ControlledVessel, SwitchControlEvent, SwitchControlType},
+};
 
 pub struct InputParser;
 impl Plugin for InputParser {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, (weapons_input, movement_input));
+        app.add_plugins((BindingsPlugin, ControlPlugin, CommandPlugin))
+            .add_systems(
+                Update,
+                (
+                    (weapons_input, movement_input).run_if(resource_equals(InputMode::Flight)),
+                    targeting_input,
+                    camera_input,
+                    control_input,
+                ),
+            );
     }
 }
 pub fn movement_input(
-    keys: Res<ButtonInput<KeyCode>>,
+    controlled_vessel: Res<ControlledVessel>,
+    action_input: ActionInput,
     mut movement_events: EventWriter<MovementEvent>,
 ) {
-    if keys.pressed(KeyCode::KeyW) {
-        println!("sending forward event");
-        movement_events.send(MovementEvent {
-            movement_type: MovementType::Forward,
-            vessel_id: VesselID {
-                player: Player::Host,
-                id: 0,
-            },
-        }); // W is being held down
-    }
-    if keys.pressed(KeyCode::KeyS) {
+    for action in action_input.pressed() {
+        let Action::Move(movement_type) = action else {
+            continue;
+        };
+        if *movement_type == MovementType::ToggleFlightAssist {
+            continue;
+        }
         movement_events.send(MovementEvent {
-            movement_type: MovementType::Backward,
-            vessel_id: VesselID {
-                player: Player::Host,
-                id: 0,
-            },
-        }); // W is being held down
+            movement_type: movement_type.clone(),
+            vessel_id: controlled_vessel.vessel_id.clone(),
+            magnitude: 1.0,
+        });
     }
-    if keys.pressed(KeyCode::KeyA) {
+    for (movement_type, magnitude) in action_input.axis_movements() {
         movement_events.send(MovementEvent {
-            movement_type: MovementType::TurnLeft,
-            vessel_id: VesselID {
-                player: Player::Host,
-                id: 0,
-            },
+            movement_type,
+            vessel_id: controlled_vessel.vessel_id.clone(),
+            magnitude,
         });
     }
-    if keys.pressed(KeyCode::KeyD) {
+    if action_input
+        .just_pressed()
+        .any(|action| *action == Action::Move(MovementType::ToggleFlightAssist))
+    {
         movement_events.send(MovementEvent {
-            movement_type: MovementType::TurnRight,
-            vessel_id: VesselID {
-                player: Player::Host,
-                id: 0,
-            },
+            movement_type: MovementType::ToggleFlightAssist,
+            vessel_id: controlled_vessel.vessel_id.clone(),
+            magnitude: 1.0,
         });
     }
 }
 pub fn weapons_input(
-    keys: Res<ButtonInput<KeyCode>>,
-    mut weapons_fire_event: EventWriter<WeaponsFireEvent>,
+    controlled_vessel: Res<ControlledVessel>,
+    action_input: ActionInput,
+    mut group_fire_events: EventWriter<WeaponGroupFireEvent>,
+) {
+    for action in action_input.pressed() {
+        let Action::FireGroup(group) = action else {
+            continue;
+        };
+        group_fire_events.send(WeaponGroupFireEvent {
+            vessel_id: controlled_vessel.vessel_id.clone(),
+            group: *group,
+        });
+    }
+}
+pub fn targeting_input(
+    controlled_vessel: Res<ControlledVessel>,
+    action_input: ActionInput,
+    mut targeting_events: EventWriter<TargetingEvent>,
+) {
+    for action in action_input.just_pressed() {
+        let Action::Target(targeting_type) = action else {
+            continue;
+        };
+        targeting_events.send(TargetingEvent {
+            targeting_type: targeting_type.clone(),
+            vessel_id: controlled_vessel.vessel_id.clone(),
+        });
+    }
+}
+pub fn camera_input(
+    action_input: ActionInput,
+    mut cycle_camera_events: EventWriter<CycleCameraEvent>,
+) {
+    if action_input
+        .just_pressed()
+        .any(|action| *action == Action::CycleCamera)
+    {
+        cycle_camera_events.send(CycleCameraEvent);
+    }
+}
+pub fn control_input(
+    action_input: ActionInput,
+    mut switch_control_events: EventWriter<SwitchControlEvent>,
 ) {
-    if keys.pressed(KeyCode::Space) {
-        weapons_fire_event.send(WeaponsFireEvent((
-            VesselID {
-                player: Player::Host,
-                id: 0,
-            },
-            WeaponStats {
-                weapons_type: WeaponsType::Plasma,
-                color: LinearRgba::rgb(0.0, 255.0, 0.0),
-                velocity: Vec3 {
-                    x: 10.0,
-                    y: 0.0,
-                    z: 0.0,
-                },
-            },
-        )));
+    for action in action_input.just_pressed() {
+        let switch_type = match action {
+            Action::NextVessel => SwitchControlType::Next,
+            Action::PreviousVessel => SwitchControlType::Previous,
+            _ => continue,
+        };
+        switch_control_events.send(SwitchControlEvent { switch_type });
     }
 }
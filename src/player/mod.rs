@@ -1,3 +1,7 @@
+pub mod bindings;
 pub mod camera;
+pub mod command;
+pub mod control;
+pub mod hud;
 pub mod input;
 pub mod player;
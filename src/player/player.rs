@@ -1,5 +1,5 @@
 use bevy::prelude::*;
-#[derive(Component, PartialEq, Eq, Clone)]
+#[derive(Component, PartialEq, Eq, Hash, Clone, Debug)]
 pub enum Player {
     Host,
     AI(u32),
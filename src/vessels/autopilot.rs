@@ -0,0 +1,450 @@
+use bevy::prelude::*;
+
+use crate::player::control::Helm;
+
+use super::{
+    formations::Wingman,
+    movements::{MovementEvent, MovementType, VelocityVector},
+    navigation::Navigation,
+    orders::{Order, OrderQueue},
+    vessels::{VesselDefinition, VesselID},
+    weapon_groups::WeaponGroupFireEvent,
+};
+
+/// Distance from a destination at which a vessel counts as arrived
+const ARRIVAL_RADIUS: f32 = 30.0;
+/// Speed below which a vessel at its destination counts as stopped
+const ARRIVAL_SPEED: f32 = 2.0;
+/// Distance behind the leader a following vessel keeps
+const FOLLOW_DISTANCE: f32 = 80.0;
+/// Distance from its station beyond which a vessel heads straight for it, and how far ahead of the
+/// station it aims once there
+const STATION_LOOKAHEAD: f32 = 80.0;
+/// Speed a station keeping vessel adds per unit of distance it is out of its station
+const STATION_KEEPING_GAIN: f32 = 0.5;
+/// Angle in radians along the orbit an orbiting vessel aims ahead of itself
+const ORBIT_LEAD_ANGLE: f32 = 0.4;
+/// Rate in radians per second at which an orbiting vessel circles its target
+const ORBIT_RATE: f32 = 0.2;
+/// Largest angle in radians between nose and destination at which the autopilot thrusts forward
+const THRUST_CONE: f32 = 0.5;
+/// Largest angle in radians between nose and target at which an attacking vessel opens fire
+const FIRING_CONE: f32 = 0.15;
+/// Fraction of the primary weapon's range an attacking vessel closes to
+const ATTACK_RANGE_FRACTION: f32 = 0.6;
+
+/// Movement commands with their magnitude that turn the nose towards `destination` and fly there,
+/// stopping within `arrival_radius`
+pub fn steer_towards(
+    vessel_transform: &Transform,
+    vessel_velocity: &VelocityVector,
+    vessel_definition: &VesselDefinition,
+    destination: Vec3,
+    arrival_radius: f32,
+) -> Vec<(MovementType, f32)> {
+    let distance = vessel_transform.translation.distance(destination);
+    if distance <= arrival_radius {
+        return brake(vessel_velocity, vessel_definition);
+    }
+    let (mut commands, heading_error) =
+        turn_towards(vessel_transform, vessel_velocity, destination);
+
+    let forward_speed = vessel_velocity.linear_velocity.x;
+    let deceleration = vessel_definition.movement_properties.linear_acceleration.x;
+    let stopping_distance = if forward_speed > 0.0 && deceleration > 0.0 {
+        forward_speed * forward_speed / (2.0 * deceleration)
+    } else {
+        0.0
+    };
+    // A destination inside the current turn circle can't be reached at this speed, slowing down tightens the turn
+    let inside_turn_circle = vessel_velocity.turn_radius > 0.0
+        && destination.distance(vessel_velocity.turn_circle_center) < vessel_velocity.turn_radius;
+    if stopping_distance >= distance - arrival_radius
+        || (inside_turn_circle && heading_error > THRUST_CONE)
+    {
+        commands.push((MovementType::Backward, 1.0));
+    } else if heading_error <= THRUST_CONE {
+        commands.push((MovementType::Forward, 1.0));
+    }
+    commands
+}
+
+/// Like [`steer_towards`], but along the path `navigation` plans around obstacles
+pub fn navigate_towards(
+    vessel_transform: &Transform,
+    vessel_velocity: &VelocityVector,
+    vessel_definition: &VesselDefinition,
+    navigation: &Navigation,
+    destination: Vec3,
+    arrival_radius: f32,
+) -> Vec<(MovementType, f32)> {
+    let path = navigation.path(
+        vessel_transform.translation,
+        destination,
+        vessel_definition.collider_half_extents.length(),
+    );
+    let [waypoint, _, ..] = path[..] else {
+        return steer_towards(
+            vessel_transform,
+            vessel_velocity,
+            vessel_definition,
+            path.first().copied().unwrap_or(destination),
+            arrival_radius,
+        );
+    };
+    // Fly through the detours no faster than still allows stopping at the end of the path
+    let remaining = vessel_transform.translation.distance(waypoint)
+        + path
+            .windows(2)
+            .map(|leg| leg[0].distance(leg[1]))
+            .sum::<f32>();
+    let deceleration = vessel_definition.movement_properties.linear_acceleration.x;
+    cruise_towards(
+        vessel_transform,
+        vessel_velocity,
+        vessel_definition,
+        waypoint,
+        (2.0 * deceleration * (remaining - arrival_radius).max(0.0)).sqrt(),
+    )
+}
+
+/// Movement commands that turn the nose towards `aim_point` while holding a forward speed of `speed`
+pub fn cruise_towards(
+    vessel_transform: &Transform,
+    vessel_velocity: &VelocityVector,
+    vessel_definition: &VesselDefinition,
+    aim_point: Vec3,
+    speed: f32,
+) -> Vec<(MovementType, f32)> {
+    let (mut commands, heading_error) = turn_towards(vessel_transform, vessel_velocity, aim_point);
+    let acceleration = vessel_definition.movement_properties.linear_acceleration.x;
+    let speed_error = speed - vessel_velocity.linear_velocity.x;
+    if acceleration <= 0.0 || speed_error.abs() <= 0.1 {
+        return commands;
+    }
+    let magnitude = (speed_error.abs() / acceleration).min(1.0);
+    if speed_error < 0.0 {
+        commands.push((MovementType::Backward, magnitude));
+    } else if heading_error <= THRUST_CONE {
+        // Speeding up only while facing the aim point keeps the turn radius small
+        commands.push((MovementType::Forward, magnitude));
+    }
+    commands
+}
+
+/// Movement commands that hold a station moving along `heading` at `leader_speed` with a leader.
+/// Out of position the vessel heads for the station, on station it flies the leader's heading.
+pub fn keep_station(
+    vessel_transform: &Transform,
+    vessel_velocity: &VelocityVector,
+    vessel_definition: &VesselDefinition,
+    station: Vec3,
+    heading: Vec3,
+    leader_speed: f32,
+) -> Vec<(MovementType, f32)> {
+    let to_station = station - vessel_transform.translation;
+    let aim_point = if to_station.length() > STATION_LOOKAHEAD {
+        station
+    } else {
+        station + heading * STATION_LOOKAHEAD
+    };
+    // A station inside the turn circle is only reached by slowing down so the turn tightens
+    let inside_turn_circle = vessel_velocity.turn_radius > 0.0
+        && station.distance(vessel_velocity.turn_circle_center) < vessel_velocity.turn_radius;
+    let speed = if inside_turn_circle && to_station.length() > STATION_LOOKAHEAD {
+        0.0
+    } else {
+        let station_error = to_station.dot(vessel_transform.rotation * Vec3::X);
+        (leader_speed + station_error * STATION_KEEPING_GAIN).max(0.0)
+    };
+    cruise_towards(
+        vessel_transform,
+        vessel_velocity,
+        vessel_definition,
+        aim_point,
+        speed,
+    )
+}
+
+/// Yaw and pitch commands that point the nose at `aim_point`, with the remaining angle between them
+pub fn turn_towards(
+    vessel_transform: &Transform,
+    vessel_velocity: &VelocityVector,
+    aim_point: Vec3,
+) -> (Vec<(MovementType, f32)>, f32) {
+    let local_aim_point =
+        vessel_transform.rotation.inverse() * (aim_point - vessel_transform.translation);
+    let yaw_error = (-local_aim_point.z).atan2(local_aim_point.x);
+    let pitch_error = local_aim_point
+        .y
+        .atan2(Vec2::new(local_aim_point.x, local_aim_point.z).length());
+    // Proportional turn damped by the current rotation, so the nose settles instead of overshooting
+    let yaw_command = (2.0 * yaw_error - vessel_velocity.angular_velocity.y).clamp(-1.0, 1.0);
+    let pitch_command = (2.0 * pitch_error - vessel_velocity.angular_velocity.z).clamp(-1.0, 1.0);
+    (
+        vec![
+            signed_command(yaw_command, MovementType::TurnLeft, MovementType::TurnRight),
+            signed_command(
+                pitch_command,
+                MovementType::PitchUp,
+                MovementType::PitchDown,
+            ),
+        ],
+        Vec3::X.angle_between(local_aim_point),
+    )
+}
+
+/// Movement commands that bring the vessel to a stop
+pub fn brake(
+    vessel_velocity: &VelocityVector,
+    vessel_definition: &VesselDefinition,
+) -> Vec<(MovementType, f32)> {
+    let acceleration = vessel_definition.movement_properties.linear_acceleration;
+    let velocity = vessel_velocity.linear_velocity;
+    [
+        (
+            velocity.x,
+            acceleration.x,
+            MovementType::Backward,
+            MovementType::Forward,
+        ),
+        (
+            velocity.y,
+            acceleration.y,
+            MovementType::StrafeDown,
+            MovementType::StrafeUp,
+        ),
+        (
+            velocity.z,
+            acceleration.z,
+            MovementType::StrafeLeft,
+            MovementType::StrafeRight,
+        ),
+    ]
+    .into_iter()
+    .filter(|(speed, acceleration, _, _)| speed.abs() > 0.1 && *acceleration > 0.0)
+    .map(
+        |(speed, acceleration, against_positive, against_negative)| {
+            // Ease off as the remaining speed can be taken out within one second
+            let magnitude = (speed.abs() / acceleration).min(1.0);
+            if speed > 0.0 {
+                (against_positive, magnitude)
+            } else {
+                (against_negative, magnitude)
+            }
+        },
+    )
+    .collect()
+}
+
+/// The `positive` movement for positive commands and the `negative` one for negative commands
+pub fn signed_command(
+    command: f32,
+    positive: MovementType,
+    negative: MovementType,
+) -> (MovementType, f32) {
+    if command >= 0.0 {
+        (positive, command)
+    } else {
+        (negative, -command)
+    }
+}
+
+/// Movement commands carrying out `order` and whether to open fire, `None` once the order is complete
+/// or its target is gone
+fn fly_order(
+    order: &mut Order,
+    vessel_transform: &Transform,
+    vessel_velocity: &VelocityVector,
+    vessel_definition: &VesselDefinition,
+    others: &Query<(&Transform, &VelocityVector), With<VesselID>>,
+    navigation: &Navigation,
+) -> Option<(Vec<(MovementType, f32)>, bool)> {
+    let position = vessel_transform.translation;
+    let speed = vessel_velocity.linear_velocity.length();
+    let mut open_fire = false;
+    let commands = match order {
+        Order::MoveTo(destination) => {
+            if position.distance(*destination) <= ARRIVAL_RADIUS && speed <= ARRIVAL_SPEED {
+                return None;
+            }
+            navigate_towards(
+                vessel_transform,
+                vessel_velocity,
+                vessel_definition,
+                navigation,
+                *destination,
+                ARRIVAL_RADIUS,
+            )
+        }
+        Order::Approach { target, distance } => {
+            let (target_transform, _) = others.get(*target).ok()?;
+            if position.distance(target_transform.translation) <= *distance
+                && speed <= ARRIVAL_SPEED
+            {
+                return None;
+            }
+            navigate_towards(
+                vessel_transform,
+                vessel_velocity,
+                vessel_definition,
+                navigation,
+                target_transform.translation,
+                *distance,
+            )
+        }
+        Order::Orbit { target, radius } => {
+            let (target_transform, _) = others.get(*target).ok()?;
+            let axis = target_transform.rotation * Vec3::Y;
+            let radial = (position - target_transform.translation)
+                .reject_from_normalized(axis)
+                .try_normalize()
+                .unwrap_or(target_transform.rotation * Vec3::X);
+            let aim_point = target_transform.translation
+                + Quat::from_axis_angle(axis, ORBIT_LEAD_ANGLE) * radial * *radius;
+            cruise_towards(
+                vessel_transform,
+                vessel_velocity,
+                vessel_definition,
+                aim_point,
+                *radius * ORBIT_RATE,
+            )
+        }
+        Order::MatchVelocity(target) => {
+            let (target_transform, target_velocity) = others.get(*target).ok()?;
+            let target_velocity = target_transform.rotation * target_velocity.linear_velocity;
+            if target_velocity.length() <= ARRIVAL_SPEED {
+                brake(vessel_velocity, vessel_definition)
+            } else {
+                cruise_towards(
+                    vessel_transform,
+                    vessel_velocity,
+                    vessel_definition,
+                    position + target_velocity,
+                    target_velocity.length(),
+                )
+            }
+        }
+        Order::Follow(leader) => {
+            let (leader_transform, leader_velocity) = others.get(*leader).ok()?;
+            let leader_forward = leader_transform.rotation * Vec3::X;
+            keep_station(
+                vessel_transform,
+                vessel_velocity,
+                vessel_definition,
+                leader_transform.translation - leader_forward * FOLLOW_DISTANCE,
+                leader_forward,
+                leader_velocity.linear_velocity.x,
+            )
+        }
+        Order::Attack(target) => {
+            let (target_transform, _) = others.get(*target).ok()?;
+            let attack_range = vessel_definition
+                .weapon_groups
+                .first()
+                .and_then(|weapon_group| weapon_group.weapons.first())
+                .and_then(|weapons_type| vessel_definition.weapon_stats(weapons_type))
+                .map_or(ARRIVAL_RADIUS, |weapon_stats| {
+                    weapon_stats.range * ATTACK_RANGE_FRACTION
+                });
+            if position.distance(target_transform.translation) > attack_range {
+                navigate_towards(
+                    vessel_transform,
+                    vessel_velocity,
+                    vessel_definition,
+                    navigation,
+                    target_transform.translation,
+                    attack_range,
+                )
+            } else {
+                // In range: hold position and keep the guns on the target
+                let (mut commands, heading_error) = turn_towards(
+                    vessel_transform,
+                    vessel_velocity,
+                    target_transform.translation,
+                );
+                open_fire = heading_error <= FIRING_CONE;
+                commands.extend(brake(vessel_velocity, vessel_definition));
+                commands
+            }
+        }
+        Order::Patrol { waypoints, next } => {
+            if waypoints.is_empty() {
+                return None;
+            }
+            if position.distance(waypoints[*next % waypoints.len()]) <= ARRIVAL_RADIUS {
+                *next = (*next + 1) % waypoints.len();
+            }
+            navigate_towards(
+                vessel_transform,
+                vessel_velocity,
+                vessel_definition,
+                navigation,
+                waypoints[*next % waypoints.len()],
+                ARRIVAL_RADIUS,
+            )
+        }
+        Order::Hold => brake(vessel_velocity, vessel_definition),
+    };
+    Some((commands, open_fire))
+}
+
+/// Flies every vessel by the front of its order queue, dropping orders as they complete.
+/// Vessels without orders come to a stop, wingmen are left to their formation and hand-flown vessels to
+/// the player.
+pub fn fly_orders(
+    helm: Helm,
+    mut movement_events: EventWriter<MovementEvent>,
+    mut group_fire_events: EventWriter<WeaponGroupFireEvent>,
+    mut vessels: Query<
+        (
+            &VesselID,
+            &Transform,
+            &VelocityVector,
+            &VesselDefinition,
+            &mut OrderQueue,
+        ),
+        Without<Wingman>,
+    >,
+    others: Query<(&Transform, &VelocityVector), With<VesselID>>,
+    navigation: Navigation,
+) {
+    for (vessel_id, vessel_transform, vessel_velocity, vessel_definition, mut order_queue) in
+        vessels.iter_mut()
+    {
+        if helm.is_hand_flown(vessel_id) {
+            continue;
+        }
+        let (commands, open_fire) = loop {
+            let Some(order) = order_queue.0.front_mut() else {
+                break (brake(vessel_velocity, vessel_definition), false);
+            };
+            match fly_order(
+                order,
+                vessel_transform,
+                vessel_velocity,
+                vessel_definition,
+                &others,
+                &navigation,
+            ) {
+                Some(flight) => break flight,
+                None => {
+                    order_queue.0.pop_front();
+                }
+            }
+        };
+        if open_fire {
+            group_fire_events.send(WeaponGroupFireEvent {
+                vessel_id: vessel_id.clone(),
+                group: 0,
+            });
+        }
+        for (movement_type, magnitude) in commands {
+            movement_events.send(MovementEvent {
+                movement_type,
+                vessel_id: vessel_id.clone(),
+                magnitude,
+            });
+        }
+    }
+}
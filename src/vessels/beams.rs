@@ -0,0 +1,137 @@
+use std::f32::consts::PI;
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use bevy_rapier3d::prelude::*;
+use serde::Deserialize;
+
+use super::{
+    damage::{DamageEvent, Hull},
+    vessels::VesselID,
+    weapons::WeaponStats,
+};
+
+/// Timing of continuous weapons, their `WeaponStats::damage` is dealt per second while firing
+#[derive(Clone, Deserialize)]
+pub struct BeamProperties {
+    /// Seconds between the trigger and the beam reaching full power
+    pub charge_time: f32,
+    /// Seconds the beam keeps firing once charged
+    pub duration: f32,
+    /// Degrees the beam sweeps across the mount's yaw axis over its duration
+    pub sweep: f32,
+    pub width: f32,
+}
+
+/// A beam attached to a hardpoint of its launcher
+#[derive(Component)]
+pub struct Beam {
+    pub launcher: Entity,
+    pub vessel_id: VesselID,
+    /// Hardpoint transform relative to the launcher, or the turret the beam is mounted on
+    pub mount: Transform,
+    pub turret: Option<Entity>,
+    pub charge: Timer,
+    pub duration: Timer,
+}
+
+pub fn spawn_beam(
+    commands: &mut Commands,
+    beam_mesh: Handle<Mesh>,
+    beam_material: Handle<StandardMaterial>,
+    weapon_stats: &WeaponStats,
+    beam: Beam,
+) {
+    commands.spawn((
+        PbrBundle {
+            mesh: beam_mesh,
+            material: beam_material,
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        weapon_stats.clone(),
+        beam,
+    ));
+}
+
+/// The vessels and turrets beams are fired from
+#[derive(SystemParam)]
+pub struct BeamMounts<'w, 's> {
+    launchers: Query<'w, 's, &'static Transform, (With<VesselID>, Without<Beam>)>,
+    turrets: Query<'w, 's, &'static Transform, (Without<VesselID>, Without<Beam>)>,
+}
+
+/// Moves beams with their hardpoint, stretches them up to the first obstacle and deals damage to it
+pub fn update_beams(
+    mut commands: Commands,
+    time: Res<Time>,
+    rapier_context: Res<RapierContext>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut beams: Query<(
+        Entity,
+        &mut Beam,
+        &WeaponStats,
+        &mut Transform,
+        &mut Visibility,
+    )>,
+    mounts: BeamMounts,
+    hulls: Query<(), With<Hull>>,
+) {
+    for (beam_entity, mut beam, weapon_stats, mut beam_transform, mut visibility) in
+        beams.iter_mut()
+    {
+        let Some(beam_properties) = &weapon_stats.beam else {
+            continue;
+        };
+        let Ok(launcher_transform) = mounts.launchers.get(beam.launcher) else {
+            commands.entity(beam_entity).despawn_recursive();
+            continue;
+        };
+        if !beam.charge.tick(time.delta()).finished() {
+            continue;
+        }
+        if beam.duration.tick(time.delta()).finished() {
+            commands.entity(beam_entity).despawn_recursive();
+            continue;
+        }
+
+        let mount = beam
+            .turret
+            .and_then(|turret| mounts.turrets.get(turret).ok())
+            .unwrap_or(&beam.mount);
+        let sweep_angle = (beam.duration.fraction() - 0.5) * beam_properties.sweep * PI / 180.0;
+        let direction = launcher_transform.rotation
+            * mount.rotation
+            * Quat::from_rotation_y(sweep_angle)
+            * Vec3::X;
+        let origin = launcher_transform.transform_point(mount.translation);
+
+        let hit = rapier_context.cast_ray(
+            origin,
+            direction,
+            weapon_stats.range,
+            true,
+            QueryFilter::default()
+                .exclude_sensors()
+                .exclude_rigid_body(beam.launcher),
+        );
+        let length = hit.map_or(weapon_stats.range, |(_, distance)| distance);
+        if let Some((hit_entity, _)) = hit {
+            if hulls.contains(hit_entity) {
+                damage_events.send(DamageEvent {
+                    target: hit_entity,
+                    attacker: beam.vessel_id.clone(),
+                    weapons_type: Some(weapon_stats.weapons_type.clone()),
+                    damage: weapon_stats.damage * time.delta_seconds(),
+                    origin: origin + direction * length,
+                });
+            }
+        }
+
+        *visibility = Visibility::Visible;
+        *beam_transform = Transform {
+            translation: origin + direction * length / 2.0,
+            rotation: Quat::from_rotation_arc(Vec3::Y, direction),
+            scale: Vec3::new(beam_properties.width, length, beam_properties.width),
+        };
+    }
+}
@@ -0,0 +1,255 @@
+use bevy::{
+    ecs::query::QueryData,
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use bevy_rapier3d::prelude::*;
+
+use crate::{
+    physics::collisions::{
+        report_collisions, report_contact_forces, ProjectileImpactEvent, VesselCollisionEvent,
+    },
+    player::player::Player,
+};
+
+use super::{
+    defences::{regenerate_shields, Armour, Facing, Shields},
+    subsystems::Subsystems,
+    vessels::{VesselDefinition, VesselID},
+    weapons::{Projectile, WeaponStats, WeaponsType},
+};
+
+/// Change in speed below which vessels bump into things without harm
+const RAM_SPEED: f32 = 5.0;
+/// Damage per unit of speed change above [`RAM_SPEED`]
+const RAM_DAMAGE: f32 = 2.0;
+/// Longest physics step, the smallest harmful impact exerts the least force when spread over it
+const MAX_PHYSICS_STEP: f32 = 1.0 / 60.0;
+
+/// Contact force from which a vessel of `mass` may take ram damage, lighter contacts aren't reported
+pub fn ram_threshold(mass: f32) -> ContactForceEventThreshold {
+    ContactForceEventThreshold(mass * RAM_SPEED / MAX_PHYSICS_STEP)
+}
+
+pub struct DamagePlugin;
+impl Plugin for DamagePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DamageEvent>()
+            .add_event::<VesselDestroyed>()
+            .init_resource::<KillTally>()
+            .add_systems(
+                Update,
+                (
+                    projectile_hits.after(report_collisions),
+                    ram_damage.after(report_contact_forces),
+                    apply_damage.after(projectile_hits).after(ram_damage),
+                    regenerate_shields.after(apply_damage),
+                    credit_kills.after(apply_damage),
+                ),
+            );
+    }
+}
+
+/// Structural integrity of a vessel or missile, it is destroyed once this reaches zero
+#[derive(Component)]
+pub struct Hull {
+    pub integrity: f32,
+    pub max_integrity: f32,
+}
+impl Hull {
+    pub fn new(max_integrity: f32) -> Self {
+        Hull {
+            integrity: max_integrity,
+            max_integrity,
+        }
+    }
+    /// Hull of a reloaded definition, keeping the integrity left up to the new maximum
+    pub fn refitted(&self, max_integrity: f32) -> Self {
+        Hull {
+            integrity: self.integrity.min(max_integrity),
+            max_integrity,
+        }
+    }
+}
+
+#[derive(Event)]
+pub struct DamageEvent {
+    pub target: Entity,
+    pub attacker: VesselID,
+    /// `None` for collisions, which armour doesn't resist
+    pub weapons_type: Option<WeaponsType>,
+    pub damage: f32,
+    /// World position the damage lands at, it decides which shield facing and subsystems take the hit
+    pub origin: Vec3,
+}
+
+#[derive(Event)]
+pub struct VesselDestroyed {
+    pub entity: Entity,
+    pub vessel_id: VesselID,
+    pub attacker: VesselID,
+}
+
+/// Vessels each player has destroyed
+#[derive(Resource, Default)]
+pub struct KillTally(pub HashMap<Player, u32>);
+
+/// Turns projectile impacts into damage and removes the spent projectiles
+fn projectile_hits(
+    mut commands: Commands,
+    mut projectile_impacts: EventReader<ProjectileImpactEvent>,
+    mut damage_events: EventWriter<DamageEvent>,
+    projectiles: Query<(&Projectile, &WeaponStats, &Transform)>,
+    hulls: Query<(), With<Hull>>,
+) {
+    // A projectile may touch several colliders in the same frame but only hits once
+    let mut spent_projectiles = HashSet::new();
+    for projectile_impact in projectile_impacts.read() {
+        let Ok((projectile, weapon_stats, projectile_transform)) =
+            projectiles.get(projectile_impact.projectile)
+        else {
+            continue;
+        };
+        // Shots pass through each other, only projectiles with a hull can be shot down
+        if projectiles.contains(projectile_impact.target)
+            && !hulls.contains(projectile_impact.target)
+        {
+            continue;
+        }
+        if !spent_projectiles.insert(projectile_impact.projectile) {
+            continue;
+        }
+        if hulls.contains(projectile_impact.target) {
+            damage_events.send(DamageEvent {
+                target: projectile_impact.target,
+                attacker: projectile.vessel_id.clone(),
+                weapons_type: Some(weapon_stats.weapons_type.clone()),
+                damage: weapon_stats.damage,
+                origin: projectile_transform.translation,
+            });
+        }
+        commands
+            .entity(projectile_impact.projectile)
+            .despawn_recursive();
+    }
+}
+
+/// Damages vessels by how much the impact changed their speed, so head-on rams hurt and glancing scrapes
+/// barely do. A vessel ramming into scenery is its own attacker.
+fn ram_damage(
+    mut vessel_collisions: EventReader<VesselCollisionEvent>,
+    mut damage_events: EventWriter<DamageEvent>,
+    vessels: Query<(&VesselID, &VesselDefinition)>,
+) {
+    for vessel_collision in vessel_collisions.read() {
+        let Ok((vessel_id, vessel_definition)) = vessels.get(vessel_collision.vessel) else {
+            continue;
+        };
+        let speed_change = vessel_collision.impulse / vessel_definition.mass;
+        if speed_change <= RAM_SPEED {
+            continue;
+        }
+        damage_events.send(DamageEvent {
+            target: vessel_collision.vessel,
+            attacker: vessels
+                .get(vessel_collision.other)
+                .map_or(vessel_id, |(other_id, _)| other_id)
+                .clone(),
+            weapons_type: None,
+            damage: (speed_change - RAM_SPEED) * RAM_DAMAGE,
+            origin: vessel_collision.point,
+        });
+    }
+}
+
+/// Anything with a hull, vessels also have a frame the hit lands in and may carry defences and subsystems
+#[derive(QueryData)]
+#[query_data(mutable)]
+struct DamageTarget {
+    hull: &'static mut Hull,
+    vessel_id: Option<&'static VesselID>,
+    vessel: Option<(&'static Transform, &'static VesselDefinition)>,
+    shields: Option<&'static mut Shields>,
+    armour: Option<&'static mut Armour>,
+    subsystems: Option<&'static mut Subsystems>,
+}
+
+/// Runs damage through shields and armour, whatever gets through wears down the hull
+fn apply_damage(
+    mut commands: Commands,
+    mut damage_events: EventReader<DamageEvent>,
+    mut vessel_destroyed: EventWriter<VesselDestroyed>,
+    mut hulls: Query<DamageTarget>,
+) {
+    for damage_event in damage_events.read() {
+        let Ok(DamageTargetItem {
+            mut hull,
+            vessel_id,
+            vessel,
+            shields,
+            armour,
+            subsystems,
+        }) = hulls.get_mut(damage_event.target)
+        else {
+            continue;
+        };
+        if hull.integrity <= 0.0 {
+            continue;
+        }
+        let mut damage = damage_event.damage;
+        if let Some((vessel_transform, vessel_definition)) = vessel {
+            let local_origin = vessel_transform.rotation.inverse()
+                * (damage_event.origin - vessel_transform.translation);
+            if let (Some(mut shields), Some(shield_properties)) =
+                (shields, &vessel_definition.shields)
+            {
+                let facing = Facing::from_direction(local_origin);
+                damage = shields.absorb(facing, damage, shield_properties.regeneration_delay);
+            }
+            if let (Some(mut armour), Some(armour_properties)) = (armour, &vessel_definition.armour)
+            {
+                damage = armour.absorb(
+                    armour_properties,
+                    damage_event.weapons_type.as_ref(),
+                    damage,
+                );
+            }
+            if let Some(mut subsystems) = subsystems {
+                subsystems.damage(vessel_definition, local_origin, damage);
+            }
+        }
+        hull.integrity -= damage;
+        if hull.integrity <= 0.0 {
+            if let Some(vessel_id) = vessel_id {
+                vessel_destroyed.send(VesselDestroyed {
+                    entity: damage_event.target,
+                    vessel_id: vessel_id.clone(),
+                    attacker: damage_event.attacker.clone(),
+                });
+            }
+            commands.entity(damage_event.target).despawn_recursive();
+        }
+    }
+}
+
+/// Credits the kill to the attacker's player, vessels lost to their own side or to scenery credit nobody
+fn credit_kills(
+    mut vessel_destroyed: EventReader<VesselDestroyed>,
+    mut kill_tally: ResMut<KillTally>,
+) {
+    for destroyed in vessel_destroyed.read() {
+        if destroyed.attacker.player == destroyed.vessel_id.player {
+            info!("{:?} was lost", destroyed.vessel_id);
+            continue;
+        }
+        let kills = kill_tally
+            .0
+            .entry(destroyed.attacker.player.clone())
+            .or_default();
+        *kills += 1;
+        info!(
+            "{:?} destroyed {:?}, {} kills for {:?}",
+            destroyed.attacker, destroyed.vessel_id, kills, destroyed.attacker.player
+        );
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use super::{
+    subsystems::{subsystem_efficiency, SubsystemType, Subsystems},
+    vessels::VesselDefinition,
+    weapons::WeaponsType,
+};
+
+/// Shield generator of a vessel, every facing holds `strength` on its own
+#[derive(Clone, Deserialize)]
+pub struct ShieldProperties {
+    pub strength: f32,
+    /// Strength regained per second and facing
+    pub regeneration: f32,
+    /// Seconds after the last hit before a facing starts regenerating
+    pub regeneration_delay: f32,
+}
+
+/// Armour plating that soaks up damage before it reaches the hull
+#[derive(Clone, Deserialize)]
+pub struct ArmourProperties {
+    pub integrity: f32,
+    /// Fraction of the damage of a weapons type the armour shrugs off, types without an entry are not resisted
+    #[serde(default)]
+    pub resistances: HashMap<WeaponsType, f32>,
+}
+
+/// Side of a vessel a hit comes from, relative to its nose
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Facing {
+    Fore,
+    Aft,
+    Port,
+    Starboard,
+}
+impl Facing {
+    /// Facing of a vessel that `direction`, given in the vessel's frame, points through
+    pub fn from_direction(direction: Vec3) -> Self {
+        if direction.x.abs() >= direction.z.abs() {
+            if direction.x >= 0.0 {
+                Facing::Fore
+            } else {
+                Facing::Aft
+            }
+        } else if direction.z < 0.0 {
+            Facing::Port
+        } else {
+            Facing::Starboard
+        }
+    }
+    fn index(self) -> usize {
+        match self {
+            Facing::Fore => 0,
+            Facing::Aft => 1,
+            Facing::Port => 2,
+            Facing::Starboard => 3,
+        }
+    }
+}
+
+/// Remaining strength of every shield facing
+#[derive(Component)]
+pub struct Shields {
+    pub facings: [f32; 4],
+    /// Seconds left per facing until it regenerates again
+    pub regeneration_delays: [f32; 4],
+}
+impl Shields {
+    pub fn new(shield_properties: &ShieldProperties) -> Self {
+        Shields {
+            facings: [shield_properties.strength; 4],
+            regeneration_delays: [0.0; 4],
+        }
+    }
+    /// Shields of a reloaded definition, keeping every facing's strength and delay up to the new maximums
+    pub fn refitted(&self, shield_properties: &ShieldProperties) -> Self {
+        Shields {
+            facings: self
+                .facings
+                .map(|strength| strength.min(shield_properties.strength)),
+            regeneration_delays: self
+                .regeneration_delays
+                .map(|delay| delay.min(shield_properties.regeneration_delay)),
+        }
+    }
+    /// Absorbs as much of `damage` as the facing holds and returns what gets through
+    pub fn absorb(&mut self, facing: Facing, damage: f32, regeneration_delay: f32) -> f32 {
+        let strength = &mut self.facings[facing.index()];
+        let absorbed = damage.min(*strength);
+        *strength -= absorbed;
+        self.regeneration_delays[facing.index()] = regeneration_delay;
+        damage - absorbed
+    }
+}
+
+#[derive(Component)]
+pub struct Armour {
+    pub integrity: f32,
+}
+impl Armour {
+    pub fn new(armour_properties: &ArmourProperties) -> Self {
+        Armour {
+            integrity: armour_properties.integrity,
+        }
+    }
+    /// Armour of a reloaded definition, keeping the integrity left up to the new maximum
+    pub fn refitted(&self, armour_properties: &ArmourProperties) -> Self {
+        Armour {
+            integrity: self.integrity.min(armour_properties.integrity),
+        }
+    }
+    /// Reduces `damage` by the resistance while the armour holds and returns what gets through to the hull
+    pub fn absorb(
+        &mut self,
+        armour_properties: &ArmourProperties,
+        weapons_type: Option<&WeaponsType>,
+        damage: f32,
+    ) -> f32 {
+        if self.integrity <= 0.0 {
+            return damage;
+        }
+        let resistance = weapons_type
+            .and_then(|weapons_type| armour_properties.resistances.get(weapons_type))
+            .copied()
+            .unwrap_or(0.0);
+        let mitigated = damage * (1.0 - resistance);
+        let absorbed = mitigated.min(self.integrity);
+        self.integrity -= absorbed;
+        mitigated - absorbed
+    }
+}
+
+/// Recharges shield facings that have not been hit for a while
+pub fn regenerate_shields(
+    time: Res<Time>,
+    mut vessels: Query<(&VesselDefinition, &mut Shields, Option<&Subsystems>)>,
+) {
+    for (vessel_definition, mut shields, subsystems) in vessels.iter_mut() {
+        let Some(shield_properties) = &vessel_definition.shields else {
+            continue;
+        };
+        let regeneration = shield_properties.regeneration
+            * subsystem_efficiency(
+                subsystems,
+                vessel_definition,
+                &SubsystemType::ShieldGenerator,
+            );
+        let shields = shields.as_mut();
+        for (strength, delay) in shields
+            .facings
+            .iter_mut()
+            .zip(shields.regeneration_delays.iter_mut())
+        {
+            if *delay > 0.0 {
+                *delay = (*delay - time.delta_seconds()).max(0.0);
+                continue;
+            }
+            *strength =
+                (*strength + regeneration * time.delta_seconds()).min(shield_properties.strength);
+        }
+    }
+}
@@ -0,0 +1,267 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::player::control::Helm;
+
+use super::{
+    autopilot::keep_station,
+    movements::{MovementEvent, VelocityVector},
+    vessels::{VesselDefinition, VesselID},
+};
+
+/// Slot spacing as a multiple of the largest member's collider size
+const FORMATION_SPACING: f32 = 4.0;
+
+/// Distance between neighbouring slots that keeps the largest of `members` clear of the others
+pub fn slot_spacing<'a>(members: impl Iterator<Item = &'a VesselDefinition>) -> f32 {
+    members
+        .map(|vessel_definition| vessel_definition.collider_half_extents.length())
+        .fold(0.0, f32::max)
+        * FORMATION_SPACING
+}
+
+/// Arrangement of the wingmen around the leader
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum FormationShape {
+    /// Side by side with the leader in the middle
+    LineAbreast,
+    /// Trailing behind the leader to both sides
+    Wedge,
+    /// One behind the other
+    Column,
+    /// Spread over a sphere around the leader
+    Sphere,
+}
+impl FormationShape {
+    /// Offset of a slot from the leader in the leader's frame, slot 0 is the leader's own
+    pub fn slot_offset(&self, slot: usize, size: usize, spacing: f32) -> Vec3 {
+        if slot == 0 {
+            return Vec3::ZERO;
+        }
+        // Wingmen alternate between port and starboard, each pair one rank further out
+        let rank = slot.div_ceil(2) as f32;
+        let side = if slot % 2 == 1 { -1.0 } else { 1.0 };
+        match self {
+            FormationShape::LineAbreast => Vec3::new(0.0, 0.0, side * rank * spacing),
+            FormationShape::Wedge => Vec3::new(-rank * spacing, 0.0, side * rank * spacing),
+            FormationShape::Column => Vec3::new(-(slot as f32) * spacing, 0.0, 0.0),
+            FormationShape::Sphere => {
+                // A Fibonacci lattice spreads the wingmen evenly, the sphere grows with their number
+                let wingmen = size.saturating_sub(1).max(1) as f32;
+                let radius = spacing * (wingmen / 4.0).sqrt().max(1.0);
+                let height = 1.0 - 2.0 * (slot as f32 - 0.5) / wingmen;
+                let ring = (1.0 - height * height).max(0.0).sqrt();
+                let angle = slot as f32 * PI * (3.0 - 5.0_f32.sqrt());
+                Vec3::new(ring * angle.cos(), height, ring * angle.sin()) * radius
+            }
+        }
+    }
+}
+
+/// A group of vessels flying together, the first member leads and the others hold their slots
+#[derive(Component)]
+pub struct Formation {
+    pub shape: FormationShape,
+    pub spacing: f32,
+    pub members: Vec<Entity>,
+}
+
+/// Marks a formation member that holds its slot instead of flying its own orders
+#[derive(Component)]
+pub struct Wingman;
+
+pub enum FormationType {
+    /// Form the members into a new formation led by the first of them
+    Form(FormationShape),
+    /// Take the members out of their formations
+    Break,
+}
+#[derive(Event)]
+pub struct FormationEvent {
+    pub formation_type: FormationType,
+    pub members: Vec<Entity>,
+}
+
+pub struct FormationsPlugin;
+impl Plugin for FormationsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<FormationEvent>().add_systems(
+            Update,
+            (change_formations, promote_leaders, hold_formations).chain(),
+        );
+    }
+}
+
+fn change_formations(
+    mut commands: Commands,
+    mut formation_events: EventReader<FormationEvent>,
+    mut formations: Query<&mut Formation>,
+    vessels: Query<&VesselDefinition>,
+) {
+    for formation_event in formation_events.read() {
+        // A vessel flies in one formation at a time
+        for mut formation in formations.iter_mut() {
+            formation
+                .members
+                .retain(|member| !formation_event.members.contains(member));
+        }
+        for member in formation_event.members.iter() {
+            if let Some(mut member_commands) = commands.get_entity(*member) {
+                member_commands.remove::<Wingman>();
+            }
+        }
+        let FormationType::Form(shape) = formation_event.formation_type else {
+            continue;
+        };
+        commands.spawn(Formation {
+            shape,
+            spacing: slot_spacing(
+                formation_event
+                    .members
+                    .iter()
+                    .filter_map(|member| vessels.get(*member).ok()),
+            ),
+            members: formation_event.members.clone(),
+        });
+    }
+}
+
+/// Drops destroyed members so the next in line takes the lead, and disbands formations left with one vessel
+fn promote_leaders(
+    mut commands: Commands,
+    mut formations: Query<(Entity, &mut Formation)>,
+    vessels: Query<Has<Wingman>, With<VesselID>>,
+) {
+    for (formation_entity, mut formation) in formations.iter_mut() {
+        formation.members.retain(|member| vessels.contains(*member));
+        if formation.members.len() < 2 {
+            for member in formation.members.iter() {
+                commands.entity(*member).remove::<Wingman>();
+            }
+            commands.entity(formation_entity).despawn();
+            continue;
+        }
+        for (slot, member) in formation.members.iter().enumerate() {
+            let is_wingman = vessels.get(*member).unwrap_or(false);
+            if slot == 0 && is_wingman {
+                commands.entity(*member).remove::<Wingman>();
+            } else if slot > 0 && !is_wingman {
+                commands.entity(*member).insert(Wingman);
+            }
+        }
+    }
+}
+
+/// Flies every wingman to its slot, unless the player flies it by hand
+fn hold_formations(
+    helm: Helm,
+    mut movement_events: EventWriter<MovementEvent>,
+    formations: Query<&Formation>,
+    vessels: Query<(&VesselID, &Transform, &VelocityVector, &VesselDefinition)>,
+) {
+    for formation in formations.iter() {
+        let Some((_, leader_transform, leader_velocity, _)) = formation
+            .members
+            .first()
+            .and_then(|leader| vessels.get(*leader).ok())
+        else {
+            continue;
+        };
+        let leader_forward = leader_transform.rotation * Vec3::X;
+        for (slot, member) in formation.members.iter().enumerate().skip(1) {
+            let Ok((vessel_id, vessel_transform, vessel_velocity, vessel_definition)) =
+                vessels.get(*member)
+            else {
+                continue;
+            };
+            if helm.is_hand_flown(vessel_id) {
+                continue;
+            }
+            let station = leader_transform.translation
+                + leader_transform.rotation
+                    * formation
+                        .shape
+                        .slot_offset(slot, formation.members.len(), formation.spacing);
+            let commands = keep_station(
+                vessel_transform,
+                vessel_velocity,
+                vessel_definition,
+                station,
+                leader_forward,
+                leader_velocity.linear_velocity.x,
+            );
+            for (movement_type, magnitude) in commands {
+                movement_events.send(MovementEvent {
+                    movement_type,
+                    vessel_id: vessel_id.clone(),
+                    magnitude,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHAPES: [FormationShape; 4] = [
+        FormationShape::LineAbreast,
+        FormationShape::Wedge,
+        FormationShape::Column,
+        FormationShape::Sphere,
+    ];
+
+    #[test]
+    fn leader_slot_is_the_leader() {
+        for shape in SHAPES {
+            assert_eq!(shape.slot_offset(0, 5, 10.0), Vec3::ZERO);
+        }
+    }
+
+    #[test]
+    fn line_abreast_alternates_port_and_starboard() {
+        let shape = FormationShape::LineAbreast;
+        assert_eq!(shape.slot_offset(1, 5, 10.0), Vec3::new(0.0, 0.0, -10.0));
+        assert_eq!(shape.slot_offset(2, 5, 10.0), Vec3::new(0.0, 0.0, 10.0));
+        assert_eq!(shape.slot_offset(3, 5, 10.0), Vec3::new(0.0, 0.0, -20.0));
+        assert_eq!(shape.slot_offset(4, 5, 10.0), Vec3::new(0.0, 0.0, 20.0));
+    }
+
+    #[test]
+    fn wedge_trails_behind_both_sides() {
+        let shape = FormationShape::Wedge;
+        assert_eq!(shape.slot_offset(1, 5, 10.0), Vec3::new(-10.0, 0.0, -10.0));
+        assert_eq!(shape.slot_offset(2, 5, 10.0), Vec3::new(-10.0, 0.0, 10.0));
+        assert_eq!(shape.slot_offset(3, 5, 10.0), Vec3::new(-20.0, 0.0, -20.0));
+    }
+
+    #[test]
+    fn column_lines_up_behind_the_leader() {
+        let shape = FormationShape::Column;
+        for slot in 1..5 {
+            assert_eq!(
+                shape.slot_offset(slot, 5, 10.0),
+                Vec3::new(-(slot as f32) * 10.0, 0.0, 0.0)
+            );
+        }
+    }
+
+    #[test]
+    fn sphere_spreads_wingmen_over_its_surface() {
+        let shape = FormationShape::Sphere;
+        let offsets: Vec<Vec3> = (1..9)
+            .map(|slot| shape.slot_offset(slot, 9, 10.0))
+            .collect();
+        // Eight wingmen spread over a sphere of sqrt(8 / 4) times the spacing
+        let radius = 10.0 * 2.0_f32.sqrt();
+        for (index, offset) in offsets.iter().enumerate() {
+            assert!((offset.length() - radius).abs() < 1e-3);
+            for other in offsets[index + 1..].iter() {
+                assert!(offset.distance(*other) > 1.0);
+            }
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use bevy::prelude::*;
+
+use super::{
+    movements::VelocityVector,
+    vessels::{VesselDefinition, VesselID},
+    weapons::{Target, WeaponStats},
+};
+
+/// Where the primary weapons of a vessel have to be pointed to hit its locked target
+#[derive(Component)]
+pub struct LeadIndicator {
+    pub position: Vec3,
+}
+
+/// Point to aim at so that a shot leaving the shooter at `projectile_speed` meets a target moving at constant velocity
+///
+/// Shots inherit the shooter's velocity, so only the velocity of the target relative to the shooter matters.
+pub fn lead_point(
+    shooter_position: Vec3,
+    shooter_velocity: Vec3,
+    target_position: Vec3,
+    target_velocity: Vec3,
+    projectile_speed: f32,
+) -> Vec3 {
+    if projectile_speed <= 0.0 {
+        return target_position;
+    }
+    let relative_position = target_position - shooter_position;
+    let relative_velocity = target_velocity - shooter_velocity;
+    // Solve |relative_position + relative_velocity * t| = projectile_speed * t for the earliest t > 0
+    let a = relative_velocity.length_squared() - projectile_speed * projectile_speed;
+    let b = 2.0 * relative_position.dot(relative_velocity);
+    let c = relative_position.length_squared();
+    let time_to_impact = if a.abs() < f32::EPSILON {
+        -c / b
+    } else {
+        let discriminant = b * b - 4.0 * a * c;
+        if discriminant < 0.0 {
+            return target_position;
+        }
+        let root = discriminant.sqrt();
+        [(-b - root) / (2.0 * a), (-b + root) / (2.0 * a)]
+            .into_iter()
+            .filter(|time| *time > 0.0)
+            .fold(f32::INFINITY, f32::min)
+    };
+    if !(time_to_impact.is_finite() && time_to_impact > 0.0) {
+        return target_position;
+    }
+    target_position + relative_velocity * time_to_impact
+}
+
+/// Projectile weapons the lead indicator is computed for, the first of the first weapon group that has any
+fn primary_weapon(vessel_definition: &VesselDefinition) -> Option<&WeaponStats> {
+    vessel_definition
+        .weapon_groups
+        .iter()
+        .flat_map(|weapon_group| weapon_group.weapons.iter())
+        .filter_map(|weapons_type| vessel_definition.weapon_stats(weapons_type))
+        .chain(vessel_definition.weapons.iter())
+        .find(|weapon_stats| weapon_stats.beam.is_none())
+}
+
+/// Keeps the lead indicator of every vessel with a locked target up to date
+pub fn update_lead_indicators(
+    mut commands: Commands,
+    vessels: Query<(
+        Entity,
+        &Transform,
+        &VelocityVector,
+        &VesselDefinition,
+        Option<&Target>,
+    )>,
+    targets: Query<(&Transform, &VelocityVector), With<VesselID>>,
+) {
+    for (vessel_entity, vessel_transform, vessel_velocity, vessel_definition, target) in
+        vessels.iter()
+    {
+        let lead = target
+            .and_then(|target| targets.get(target.0).ok())
+            .zip(primary_weapon(vessel_definition))
+            .map(|((target_transform, target_velocity), weapon_stats)| {
+                lead_point(
+                    vessel_transform.translation,
+                    vessel_transform.rotation * vessel_velocity.linear_velocity,
+                    target_transform.translation,
+                    target_transform.rotation * target_velocity.linear_velocity,
+                    weapon_stats.velocity.length(),
+                )
+            });
+        match lead {
+            Some(position) => {
+                commands
+                    .entity(vessel_entity)
+                    .try_insert(LeadIndicator { position });
+            }
+            None => {
+                commands.entity(vessel_entity).remove::<LeadIndicator>();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lead_point_of_stationary_target_is_the_target() {
+        let target = Vec3::new(100.0, 20.0, -30.0);
+        let lead = lead_point(Vec3::ZERO, Vec3::ZERO, target, Vec3::ZERO, 50.0);
+        assert!(lead.distance(target) < 1e-3);
+    }
+
+    #[test]
+    fn lead_point_meets_crossing_target() {
+        let target = Vec3::new(100.0, 0.0, 0.0);
+        let target_velocity = Vec3::new(0.0, 10.0, 0.0);
+        let lead = lead_point(Vec3::ZERO, Vec3::ZERO, target, target_velocity, 100.0);
+        // The shot reaches the lead point at the same time as the target
+        let time_to_impact = lead.length() / 100.0;
+        assert!(lead.y > 0.0);
+        assert!(lead.distance(target + target_velocity * time_to_impact) < 1e-3);
+    }
+
+    #[test]
+    fn lead_point_ignores_velocity_shared_with_shooter() {
+        let velocity = Vec3::new(30.0, -5.0, 12.0);
+        let target = Vec3::new(0.0, 0.0, 200.0);
+        let lead = lead_point(Vec3::ZERO, velocity, target, velocity, 100.0);
+        assert!(lead.distance(target) < 1e-3);
+    }
+
+    #[test]
+    fn lead_point_falls_back_to_target_it_cannot_catch() {
+        let target = Vec3::new(100.0, 0.0, 0.0);
+        let lead = lead_point(
+            Vec3::ZERO,
+            Vec3::ZERO,
+            target,
+            Vec3::new(200.0, 0.0, 0.0),
+            100.0,
+        );
+        assert_eq!(lead, target);
+    }
+}
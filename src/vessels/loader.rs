@@ -0,0 +1,374 @@
+use std::path::PathBuf;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    ecs::query::QueryData,
+    prelude::*,
+};
+use bevy_rapier3d::prelude::{Collider, ColliderMassProperties};
+use thiserror::Error;
+
+use super::{
+    damage::{ram_threshold, Hull},
+    defences::{Armour, Shields},
+    spawn::complete_vessel_spawns,
+    subsystems::Subsystems,
+    turrets::Turret,
+    vessels::VesselDefinition,
+    weapons::{WeaponsState, WeaponsType},
+};
+
+pub struct VesselDefinitionPlugin;
+impl Plugin for VesselDefinitionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<VesselDefinition>()
+            .register_asset_loader(VesselDefinitionLoader)
+            .add_systems(Update, (complete_vessel_spawns, reload_vessel_definitions));
+    }
+}
+
+/// Loads `*.vessel.ron` files into [`VesselDefinition`] assets
+#[derive(Default)]
+pub struct VesselDefinitionLoader;
+
+#[derive(Debug, Error)]
+pub enum VesselDefinitionLoaderError {
+    #[error("could not read vessel definition {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("could not parse vessel definition {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: ron::error::SpannedError,
+    },
+    #[error("invalid vessel definition {path}, field `{field}`: {reason}")]
+    Invalid {
+        path: PathBuf,
+        field: String,
+        reason: String,
+    },
+}
+
+impl AssetLoader for VesselDefinitionLoader {
+    type Asset = VesselDefinition;
+    type Settings = ();
+    type Error = VesselDefinitionLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<VesselDefinition, VesselDefinitionLoaderError> {
+        let path = load_context.path().to_path_buf();
+        let mut bytes = Vec::new();
+        if let Err(source) = reader.read_to_end(&mut bytes).await {
+            return Err(VesselDefinitionLoaderError::Io { path, source });
+        }
+        let vessel_definition: VesselDefinition = match ron::de::from_bytes(&bytes) {
+            Ok(vessel_definition) => vessel_definition,
+            Err(source) => return Err(VesselDefinitionLoaderError::Parse { path, source }),
+        };
+        if let Err((field, reason)) = validate(&vessel_definition) {
+            return Err(VesselDefinitionLoaderError::Invalid {
+                path,
+                field,
+                reason,
+            });
+        }
+        Ok(vessel_definition)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["vessel.ron"]
+    }
+}
+
+/// The offending field of an invalid definition and the reason it is rejected
+type Invalid = (String, String);
+
+/// Fails with `field` unless `value` is a finite number above zero
+fn positive(field: impl Into<String>, value: f32) -> Result<(), Invalid> {
+    if value.is_finite() && value > 0.0 {
+        return Ok(());
+    }
+    Err((field.into(), "must be a positive number".to_owned()))
+}
+
+/// Fails with `field` unless `value` is a finite number of at least zero
+fn non_negative(field: impl Into<String>, value: f32) -> Result<(), Invalid> {
+    if value.is_finite() && value >= 0.0 {
+        return Ok(());
+    }
+    Err((field.into(), "must be a non-negative number".to_owned()))
+}
+
+/// Fails with `field` unless the transform has a finite translation and a unit rotation
+fn placement(field: impl Into<String>, transform: &Transform) -> Result<(), Invalid> {
+    if transform.translation.is_finite() && transform.rotation.is_normalized() {
+        return Ok(());
+    }
+    Err((
+        field.into(),
+        "translation must be finite and rotation a unit quaternion".to_owned(),
+    ))
+}
+
+/// Checks the values serde cannot, returns the offending field and the reason on failure
+fn validate(vessel_definition: &VesselDefinition) -> Result<(), Invalid> {
+    if vessel_definition.model_path.is_empty() {
+        return Err(("model_path".to_owned(), "must not be empty".to_owned()));
+    }
+    positive("mass", vessel_definition.mass)?;
+    if !(vessel_definition.collider_half_extents.is_finite()
+        && vessel_definition.collider_half_extents.min_element() > 0.0)
+    {
+        return Err((
+            "collider_half_extents".to_owned(),
+            "all extents must be positive numbers".to_owned(),
+        ));
+    }
+    positive("hull", vessel_definition.hull)?;
+    if let Some(shields) = &vessel_definition.shields {
+        non_negative("shields.strength", shields.strength)?;
+        non_negative("shields.regeneration", shields.regeneration)?;
+        non_negative("shields.regeneration_delay", shields.regeneration_delay)?;
+    }
+    if let Some(armour) = &vessel_definition.armour {
+        non_negative("armour.integrity", armour.integrity)?;
+        for (weapons_type, resistance) in armour.resistances.iter() {
+            if !(0.0..=1.0).contains(resistance) {
+                return Err((
+                    format!("armour.resistances[{:?}]", weapons_type),
+                    "must be between 0 and 1".to_owned(),
+                ));
+            }
+        }
+    }
+    let movement_properties = &vessel_definition.movement_properties;
+    for (field, acceleration) in [
+        (
+            "movement_properties.linear_acceleration",
+            movement_properties.linear_acceleration,
+        ),
+        (
+            "movement_properties.angular_acceleration",
+            movement_properties.angular_acceleration,
+        ),
+    ] {
+        if !(acceleration.is_finite() && acceleration.min_element() >= 0.0) {
+            return Err((
+                field.to_owned(),
+                "all components must be non-negative numbers".to_owned(),
+            ));
+        }
+    }
+    positive("heat_capacity", vessel_definition.heat_capacity)?;
+    non_negative("heat_dissipation", vessel_definition.heat_dissipation)?;
+    positive("sensors.range", vessel_definition.sensors.range)?;
+    non_negative("sensors.lock_time", vessel_definition.sensors.lock_time)?;
+    for (index, subsystem) in vessel_definition.subsystems.iter().enumerate() {
+        let field = format!("subsystems[{}]", index);
+        placement(format!("{}.transform", field), &subsystem.transform)?;
+        positive(format!("{}.radius", field), subsystem.radius)?;
+        positive(format!("{}.integrity", field), subsystem.integrity)?;
+    }
+    for (index, weapon_stats) in vessel_definition.weapons.iter().enumerate() {
+        let field = format!("weapons[{}]", index);
+        if vessel_definition.weapons[..index]
+            .iter()
+            .any(|other_stats| other_stats.weapons_type == weapon_stats.weapons_type)
+        {
+            return Err((
+                format!("{}.weapons_type", field),
+                format!("{:?} is defined more than once", weapon_stats.weapons_type),
+            ));
+        }
+        positive(format!("{}.range", field), weapon_stats.range)?;
+        non_negative(format!("{}.damage", field), weapon_stats.damage)?;
+        if !weapon_stats.velocity.is_finite()
+            || (weapon_stats.beam.is_none() && weapon_stats.velocity.length() <= 0.0)
+        {
+            return Err((
+                format!("{}.velocity", field),
+                "must be finite and non-zero for projectile weapons".to_owned(),
+            ));
+        }
+        if let Some(guidance) = &weapon_stats.guidance {
+            let field = format!("{}.guidance", field);
+            non_negative(format!("{}.thrust", field), guidance.thrust)?;
+            non_negative(format!("{}.turn_rate", field), guidance.turn_rate)?;
+            non_negative(format!("{}.fuel", field), guidance.fuel)?;
+            non_negative(format!("{}.proximity_fuse", field), guidance.proximity_fuse)?;
+            non_negative(format!("{}.hull", field), guidance.hull)?;
+        }
+        if let Some(beam) = &weapon_stats.beam {
+            let field = format!("{}.beam", field);
+            non_negative(format!("{}.charge_time", field), beam.charge_time)?;
+            non_negative(format!("{}.duration", field), beam.duration)?;
+            non_negative(format!("{}.width", field), beam.width)?;
+            if !beam.sweep.is_finite() {
+                return Err((
+                    format!("{}.sweep", field),
+                    "must be a finite number".to_owned(),
+                ));
+            }
+        }
+    }
+    if let Some(convergence_distance) = vessel_definition.convergence_distance {
+        positive("convergence_distance", convergence_distance)?;
+    }
+    for (index, weapon_group) in vessel_definition.weapon_groups.iter().enumerate() {
+        for weapons_type in weapon_group.weapons.iter() {
+            if !vessel_definition.hardpoints.contains_key(weapons_type) {
+                return Err((
+                    format!("weapon_groups[{}].weapons", index),
+                    format!("{:?} is not mounted on any hardpoint", weapons_type),
+                ));
+            }
+        }
+    }
+    for (weapons_type, hardpoints) in vessel_definition.hardpoints.iter() {
+        if vessel_definition.weapon_stats(weapons_type).is_none() {
+            return Err((
+                format!("hardpoints[{:?}]", weapons_type),
+                "has no stats in `weapons`".to_owned(),
+            ));
+        }
+        for (index, hardpoint) in hardpoints.iter().enumerate() {
+            let field = format!("hardpoints[{:?}][{}]", weapons_type, index);
+            placement(format!("{}.transform", field), &hardpoint.transform)?;
+            non_negative(format!("{}.cooldown", field), hardpoint.cooldown)?;
+            non_negative(format!("{}.heat", field), hardpoint.heat)?;
+            if !(hardpoint.gimbal.is_finite() && (0.0..=180.0).contains(&hardpoint.gimbal)) {
+                return Err((
+                    format!("{}.gimbal", field),
+                    "must be between 0 and 180 degrees".to_owned(),
+                ));
+            }
+            if *weapons_type == WeaponsType::PointDefence && hardpoint.turret.is_none() {
+                return Err((
+                    format!("{}.turret", field),
+                    "point-defence hardpoints must be turreted".to_owned(),
+                ));
+            }
+            if let Some(turret) = &hardpoint.turret {
+                for (limits_field, limits) in [
+                    ("yaw_limits", turret.yaw_limits),
+                    ("pitch_limits", turret.pitch_limits),
+                ] {
+                    if !(limits.is_finite() && limits.x <= limits.y) {
+                        return Err((
+                            format!("{}.turret.{}", field, limits_field),
+                            "must be finite with the minimum first".to_owned(),
+                        ));
+                    }
+                }
+                positive(
+                    format!("{}.turret.traverse_speed", field),
+                    turret.traverse_speed,
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A vessel spawned from a definition file, with the state built from the definition
+#[derive(QueryData)]
+struct ReloadedVessel {
+    entity: Entity,
+    handle: &'static Handle<VesselDefinition>,
+    vessel_definition: &'static VesselDefinition,
+    children: Option<&'static Children>,
+    hull: &'static Hull,
+    shields: Option<&'static Shields>,
+    armour: Option<&'static Armour>,
+    weapons_state: &'static WeaponsState,
+    subsystems: Option<&'static Subsystems>,
+}
+
+/// Applies edited definition files to the vessels that were spawned from them, rebuilding their defences,
+/// weapons, subsystems and turrets and swapping the model if it changed. Damage, heat, ammunition and
+/// cooldowns carry over up to the new maximums.
+fn reload_vessel_definitions(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut asset_events: EventReader<AssetEvent<VesselDefinition>>,
+    vessel_definitions: Res<Assets<VesselDefinition>>,
+    vessels: Query<ReloadedVessel>,
+    turrets: Query<(), With<Turret>>,
+) {
+    for asset_event in asset_events.read() {
+        let AssetEvent::Modified { id } = asset_event else {
+            continue;
+        };
+        let Some(reloaded_definition) = vessel_definitions.get(*id) else {
+            continue;
+        };
+        for vessel in vessels.iter() {
+            if vessel.handle.id() != *id {
+                continue;
+            }
+            // The turrets are spawned again for the reloaded hardpoints once the new weapons state is added
+            for turret in vessel
+                .children
+                .into_iter()
+                .flatten()
+                .filter(|child| turrets.contains(**child))
+            {
+                commands.entity(*turret).despawn_recursive();
+            }
+            let mut vessel_commands = commands.entity(vessel.entity);
+            if reloaded_definition.model_path != vessel.vessel_definition.model_path {
+                vessel_commands.insert(asset_server.load::<Scene>(
+                    GltfAssetLabel::Scene(0).from_asset(reloaded_definition.model_path.clone()),
+                ));
+            }
+            vessel_commands
+                .remove::<(WeaponsState, Subsystems)>()
+                .insert((
+                    reloaded_definition.clone(),
+                    Collider::cuboid(
+                        reloaded_definition.collider_half_extents.x,
+                        reloaded_definition.collider_half_extents.y,
+                        reloaded_definition.collider_half_extents.z,
+                    ),
+                    ColliderMassProperties::Mass(reloaded_definition.mass),
+                    ram_threshold(reloaded_definition.mass),
+                    vessel.hull.refitted(reloaded_definition.hull),
+                    vessel.weapons_state.refitted(reloaded_definition),
+                    vessel.subsystems.map_or_else(
+                        || Subsystems::new(reloaded_definition),
+                        |subsystems| {
+                            subsystems.refitted(vessel.vessel_definition, reloaded_definition)
+                        },
+                    ),
+                ));
+            match &reloaded_definition.shields {
+                Some(shield_properties) => {
+                    vessel_commands.insert(vessel.shields.map_or_else(
+                        || Shields::new(shield_properties),
+                        |shields| shields.refitted(shield_properties),
+                    ));
+                }
+                None => {
+                    vessel_commands.remove::<Shields>();
+                }
+            }
+            match &reloaded_definition.armour {
+                Some(armour_properties) => {
+                    vessel_commands.insert(vessel.armour.map_or_else(
+                        || Armour::new(armour_properties),
+                        |armour| armour.refitted(armour_properties),
+                    ));
+                }
+                None => {
+                    vessel_commands.remove::<Armour>();
+                }
+            }
+        }
+    }
+}
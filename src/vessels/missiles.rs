@@ -0,0 +1,160 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::Velocity;
+use serde::Deserialize;
+
+use super::{
+    damage::{DamageEvent, Hull},
+    vessels::VesselID,
+    weapons::{Projectile, WeaponStats},
+};
+
+/// Flight characteristics of self-propelled projectiles
+#[derive(Clone, Deserialize)]
+pub struct GuidanceProperties {
+    /// Acceleration while fuel lasts
+    pub thrust: f32,
+    /// Degrees per second the seeker can turn the flight path
+    pub turn_rate: f32,
+    /// Seconds of burn time, afterwards the projectile coasts
+    pub fuel: f32,
+    /// Distance to a hostile vessel at which the warhead detonates
+    pub proximity_fuse: f32,
+    /// Damage it takes to shoot the projectile down
+    pub hull: f32,
+}
+
+impl GuidanceProperties {
+    /// Seconds a projectile launched at `launch_speed` takes to cover `range`, accelerating while its fuel
+    /// lasts and coasting at the speed it reached afterwards
+    pub fn flight_time(&self, launch_speed: f32, range: f32) -> f32 {
+        let burn_distance = launch_speed * self.fuel + 0.5 * self.thrust * self.fuel * self.fuel;
+        if range < burn_distance && self.thrust > 0.0 {
+            // Solves range = launch_speed * t + thrust * t² / 2
+            return ((launch_speed * launch_speed + 2.0 * self.thrust * range).sqrt()
+                - launch_speed)
+                / self.thrust;
+        }
+        let top_speed = launch_speed + self.thrust * self.fuel;
+        self.fuel + (range - burn_distance) / top_speed
+    }
+}
+
+/// A projectile homing on the target its launcher had locked when firing
+#[derive(Component)]
+pub struct Guided {
+    pub target: Option<Entity>,
+    pub fuel: f32,
+}
+
+/// Steers guided projectiles towards their target and burns their fuel
+pub fn guide_missiles(
+    time: Res<Time>,
+    mut missiles: Query<(&mut Guided, &mut Velocity, &mut Transform, &WeaponStats)>,
+    targets: Query<&Transform, (With<VesselID>, Without<Guided>)>,
+) {
+    for (mut guided, mut velocity, mut missile_transform, weapon_stats) in missiles.iter_mut() {
+        let Some(guidance) = &weapon_stats.guidance else {
+            continue;
+        };
+        if guided.fuel <= 0.0 {
+            continue;
+        }
+        guided.fuel -= time.delta_seconds();
+
+        let Ok(heading) = Dir3::new(velocity.linvel) else {
+            continue;
+        };
+        let desired_heading = guided
+            .target
+            .and_then(|target| targets.get(target).ok())
+            .and_then(|target_transform| {
+                Dir3::new(target_transform.translation - missile_transform.translation).ok()
+            })
+            .unwrap_or(heading);
+        let max_turn = guidance.turn_rate * PI / 180.0 * time.delta_seconds();
+        let turn_angle = heading.angle_between(*desired_heading).min(max_turn);
+        let turn_axis = heading.cross(*desired_heading).normalize_or_zero();
+        let new_heading = if turn_axis == Vec3::ZERO {
+            *heading
+        } else {
+            Quat::from_axis_angle(turn_axis, turn_angle) * *heading
+        };
+
+        let speed = velocity.linvel.length() + guidance.thrust * time.delta_seconds();
+        velocity.linvel = new_heading * speed;
+        missile_transform.rotation =
+            Quat::from_rotation_arc(Vec3::X, new_heading) * Quat::from_rotation_z(PI / 2.0);
+    }
+}
+
+/// Detonates guided projectiles that come close enough to a hostile vessel, the blast spares the launcher's side
+pub fn detonate_missiles(
+    mut commands: Commands,
+    mut damage_events: EventWriter<DamageEvent>,
+    missiles: Query<(Entity, &Transform, &Projectile, &WeaponStats), With<Guided>>,
+    vessels: Query<(Entity, &VesselID, &Transform), With<Hull>>,
+) {
+    for (missile_entity, missile_transform, projectile, weapon_stats) in missiles.iter() {
+        let Some(guidance) = &weapon_stats.guidance else {
+            continue;
+        };
+        let vessels_in_blast: Vec<Entity> = vessels
+            .iter()
+            .filter(|(_, vessel_id, vessel_transform)| {
+                vessel_id.player != projectile.vessel_id.player
+                    && vessel_transform
+                        .translation
+                        .distance(missile_transform.translation)
+                        <= guidance.proximity_fuse
+            })
+            .map(|(vessel_entity, _, _)| vessel_entity)
+            .collect();
+        if vessels_in_blast.is_empty() {
+            continue;
+        }
+        for vessel_entity in vessels_in_blast {
+            damage_events.send(DamageEvent {
+                target: vessel_entity,
+                attacker: projectile.vessel_id.clone(),
+                weapons_type: Some(weapon_stats.weapons_type.clone()),
+                damage: weapon_stats.damage,
+                origin: missile_transform.translation,
+            });
+        }
+        commands.entity(missile_entity).despawn_recursive();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guidance(thrust: f32, fuel: f32) -> GuidanceProperties {
+        GuidanceProperties {
+            thrust,
+            turn_rate: 90.0,
+            fuel,
+            proximity_fuse: 10.0,
+            hull: 10.0,
+        }
+    }
+
+    #[test]
+    fn flight_time_within_burn() {
+        // 50 * 10 + 150 * 10² / 2 = 8000
+        assert!((guidance(150.0, 12.0).flight_time(50.0, 8000.0) - 10.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn flight_time_coasting_after_burnout() {
+        // 1000 units during the 10 s burn, then the remaining 1000 at 200 units per second
+        assert!((guidance(20.0, 10.0).flight_time(0.0, 2000.0) - 15.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn flight_time_without_thrust() {
+        assert!((guidance(0.0, 10.0).flight_time(50.0, 1000.0) - 20.0).abs() < 1e-3);
+    }
+}
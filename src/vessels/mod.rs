@@ -1,4 +1,19 @@
+pub mod autopilot;
+pub mod beams;
+pub mod damage;
+pub mod defences;
+pub mod formations;
+pub mod gunnery;
+pub mod loader;
+pub mod missiles;
 pub mod movements;
+pub mod navigation;
+pub mod orders;
+pub mod point_defence;
 pub mod spawn;
+pub mod subsystems;
+pub mod targeting;
+pub mod turrets;
 pub mod vessels;
+pub mod weapon_groups;
 pub mod weapons;
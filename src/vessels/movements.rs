@@ -1,11 +1,13 @@
-use std::f32::consts::PI;
-
-use bevy::prelude::*;
-// use bevy_rapier3d::rapier::pipeline::DebugColor;
+use bevy::{ecs::query::QueryData, prelude::*};
+use bevy_rapier3d::prelude::{ExternalForce, ReadMassProperties, Velocity};
+use serde::{Deserialize, Serialize};
 
 use crate::player::input::movement_input;
 
-use super::vessels::{VesselDefinition, VesselID};
+use super::{
+    subsystems::{subsystem_efficiency, SubsystemType, Subsystems},
+    vessels::{VesselDefinition, VesselID},
+};
 
 pub struct VesselMovement;
 impl Plugin for VesselMovement {
@@ -13,23 +15,58 @@ impl Plugin for VesselMovement {
         app.add_systems(
             Update,
             (
-                change_velocity.after(movement_input),
-                apply_velocity.after(change_velocity),
+                read_velocity,
+                change_velocity.after(movement_input).after(read_velocity),
             ),
         );
     }
 }
+/// Thrust and rotation commands in the vessel's local frame.
+/// The nose points along local x, local y is up and local -z is port.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
 pub enum MovementType {
     Forward,
     Backward,
+    StrafeUp,
+    StrafeDown,
+    StrafeLeft,
+    StrafeRight,
     TurnLeft,
     TurnRight,
+    PitchUp,
+    PitchDown,
+    RollLeft,
+    RollRight,
+    ToggleFlightAssist,
+}
+impl MovementType {
+    /// Unit directions of the linear and angular velocity change caused by this movement
+    fn directions(&self) -> (Vec3, Vec3) {
+        match self {
+            MovementType::Forward => (Vec3::X, Vec3::ZERO),
+            MovementType::Backward => (Vec3::NEG_X, Vec3::ZERO),
+            MovementType::StrafeUp => (Vec3::Y, Vec3::ZERO),
+            MovementType::StrafeDown => (Vec3::NEG_Y, Vec3::ZERO),
+            MovementType::StrafeLeft => (Vec3::NEG_Z, Vec3::ZERO),
+            MovementType::StrafeRight => (Vec3::Z, Vec3::ZERO),
+            MovementType::TurnLeft => (Vec3::ZERO, Vec3::Y),
+            MovementType::TurnRight => (Vec3::ZERO, Vec3::NEG_Y),
+            MovementType::PitchUp => (Vec3::ZERO, Vec3::Z),
+            MovementType::PitchDown => (Vec3::ZERO, Vec3::NEG_Z),
+            MovementType::RollLeft => (Vec3::ZERO, Vec3::NEG_X),
+            MovementType::RollRight => (Vec3::ZERO, Vec3::X),
+            MovementType::ToggleFlightAssist => (Vec3::ZERO, Vec3::ZERO),
+        }
+    }
 }
 #[derive(Event)]
 pub struct MovementEvent {
     pub movement_type: MovementType,
     pub vessel_id: VesselID,
+    /// How hard the command is given, from 0 to 1, digital inputs always give 1
+    pub magnitude: f32,
 }
+/// The vessel's velocity in its local frame, mirrored from the physics body every frame
 #[derive(Component, Default)]
 pub struct VelocityVector {
     pub linear_velocity: Vec3,
@@ -38,135 +75,166 @@ pub struct VelocityVector {
     pub turn_circle_center: Vec3,
 }
 
-#[derive(Clone)]
+/// How a vessel's velocity reacts to its rotation
+#[derive(Component, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FlightAssist {
+    /// Velocity and spin persist in world space, rotating only changes where the thrusters point
+    Newtonian,
+    /// Velocity follows the nose, sideways drift is bled off by the thrusters and rotation stops once no
+    /// longer commanded
+    #[default]
+    Assisted,
+}
+impl FlightAssist {
+    fn toggled(self) -> Self {
+        match self {
+            FlightAssist::Newtonian => FlightAssist::Assisted,
+            FlightAssist::Assisted => FlightAssist::Newtonian,
+        }
+    }
+}
+
+#[derive(Clone, Deserialize)]
 pub struct MovementProperties {
     pub linear_acceleration: Vec3,
     pub angular_acceleration: Vec3,
 }
+/// A vessel's rigid body with what limits the thrust and torque applied to it
+#[derive(QueryData)]
+#[query_data(mutable)]
+struct ThrustingVessel {
+    external_force: &'static mut ExternalForce,
+    flight_assist: &'static mut FlightAssist,
+    transform: &'static Transform,
+    velocity: &'static VelocityVector,
+    mass_properties: &'static ReadMassProperties,
+    vessel_id: &'static VesselID,
+    vessel_definition: &'static VesselDefinition,
+    subsystems: Option<&'static Subsystems>,
+}
+
+/// Turns movement events into thrust and torque on the vessel's rigid body.
+/// In assisted mode sideways drift and rotation without a command are braked using the same acceleration,
+/// Newtonian vessels keep drifting and spinning.
 fn change_velocity(
     time: Res<Time>,
     mut movement_events: EventReader<MovementEvent>,
-    mut vessels: Query<(&mut VelocityVector, &VesselID, &VesselDefinition)>,
+    mut vessels: Query<ThrustingVessel>,
 ) {
-    for (mut vessel_velocity, vessel_id, vessel_definition) in vessels.iter_mut() {
-        for movement_event in movement_events.read() {
+    let movement_events: Vec<&MovementEvent> = movement_events.read().collect();
+    for ThrustingVesselItem {
+        mut external_force,
+        mut flight_assist,
+        transform: vessel_transform,
+        velocity: vessel_velocity,
+        mass_properties,
+        vessel_id,
+        vessel_definition,
+        subsystems,
+    } in vessels.iter_mut()
+    {
+        // Damaged engines deliver only part of their thrust
+        let engine_efficiency =
+            subsystem_efficiency(subsystems, vessel_definition, &SubsystemType::Engines);
+        let mut movement_properties = vessel_definition.movement_properties.clone();
+        movement_properties.linear_acceleration *= engine_efficiency;
+        movement_properties.angular_acceleration *= engine_efficiency;
+        let mut linear_command = Vec3::ZERO;
+        let mut angular_command = Vec3::ZERO;
+        for movement_event in movement_events.iter() {
             if movement_event.vessel_id == *vessel_id {
-                match movement_event.movement_type {
-                    MovementType::Forward => {
-                        vessel_velocity.linear_velocity.x += time.delta_seconds()
-                            * vessel_definition.movement_properties.linear_acceleration.x
-                    }
-                    MovementType::Backward => {
-                        vessel_velocity.linear_velocity.x -= time.delta_seconds()
-                            * vessel_definition.movement_properties.linear_acceleration.x
-                    }
-                    MovementType::TurnLeft => {
-                        vessel_velocity.angular_velocity.y += time.delta_seconds()
-                            * vessel_definition.movement_properties.angular_acceleration.y
-                            * 2.0
-                    }
-                    MovementType::TurnRight => {
-                        vessel_velocity.angular_velocity.y -= time.delta_seconds()
-                            * vessel_definition.movement_properties.angular_acceleration.y
-                            * 2.0
-                    }
+                if let MovementType::ToggleFlightAssist = movement_event.movement_type {
+                    *flight_assist = flight_assist.toggled();
+                    continue;
                 }
+                let (linear_direction, angular_direction) =
+                    movement_event.movement_type.directions();
+                linear_command += linear_direction * movement_event.magnitude;
+                angular_command += angular_direction * movement_event.magnitude;
             }
         }
+        linear_command = linear_command.clamp(Vec3::NEG_ONE, Vec3::ONE);
+        angular_command = angular_command.clamp(Vec3::NEG_ONE, Vec3::ONE);
 
-        let angular_velocity_change =
-            time.delta_seconds() * vessel_definition.movement_properties.angular_acceleration.y;
-        if vessel_velocity.angular_velocity.y.abs() <= angular_velocity_change {
-            vessel_velocity.angular_velocity.y = 0.0
-        } else {
-            if vessel_velocity.angular_velocity.y > 0.0 {
-                vessel_velocity.angular_velocity.y -= angular_velocity_change;
-            } else if vessel_velocity.angular_velocity.y < 0.0 {
-                vessel_velocity.angular_velocity.y += angular_velocity_change;
-            }
+        let mut linear_acceleration = linear_command * movement_properties.linear_acceleration;
+        if *flight_assist == FlightAssist::Assisted {
+            let braking = brake(
+                vessel_velocity.linear_velocity,
+                movement_properties.linear_acceleration,
+                time.delta_seconds(),
+            );
+            linear_acceleration.y =
+                braking_or_command(linear_command.y, braking.y, linear_acceleration.y);
+            linear_acceleration.z =
+                braking_or_command(linear_command.z, braking.z, linear_acceleration.z);
+        }
+        let mut angular_acceleration = angular_command * movement_properties.angular_acceleration;
+        if *flight_assist == FlightAssist::Assisted {
+            let braking = brake(
+                vessel_velocity.angular_velocity,
+                movement_properties.angular_acceleration,
+                time.delta_seconds(),
+            );
+            angular_acceleration = Vec3::new(
+                braking_or_command(angular_command.x, braking.x, angular_acceleration.x),
+                braking_or_command(angular_command.y, braking.y, angular_acceleration.y),
+                braking_or_command(angular_command.z, braking.z, angular_acceleration.z),
+            );
         }
+
+        let mass_properties = mass_properties.get();
+        let inertia_frame =
+            vessel_transform.rotation * mass_properties.principal_inertia_local_frame;
+        external_force.force =
+            vessel_transform.rotation * linear_acceleration * mass_properties.mass;
+        external_force.torque = inertia_frame
+            * (mass_properties.principal_inertia
+                * (inertia_frame.inverse() * (vessel_transform.rotation * angular_acceleration)));
     }
 }
-// currently only considers rotation around the y axis
-fn calculate_turn_circle_center(
+/// Acceleration that brings each velocity component to zero without overshooting
+fn brake(velocity: Vec3, acceleration: Vec3, delta_seconds: f32) -> Vec3 {
+    if delta_seconds == 0.0 {
+        return Vec3::ZERO;
+    }
+    (-velocity / delta_seconds).clamp(-acceleration, acceleration)
+}
+fn braking_or_command(command: f32, braking: f32, commanded_acceleration: f32) -> f32 {
+    if command == 0.0 {
+        braking
+    } else {
+        commanded_acceleration
+    }
+}
+/// Returns the radius and world space center of the circle the vessel currently flies along.
+/// A vessel that does not rotate or does not move has a turn radius of zero.
+pub fn calculate_turn_circle_center(
     transform: &Transform,
     vessel_velocity: &VelocityVector,
 ) -> (f32, Vec3) {
-    let turn_radius = vessel_velocity.linear_velocity.x / vessel_velocity.angular_velocity.y * 10.0;
-
-    let mut turn_circle_center = transform.clone();
-    turn_circle_center.translation.z -= turn_radius;
+    let angular_velocity = transform.rotation * vessel_velocity.angular_velocity;
+    let linear_velocity = transform.rotation * vessel_velocity.linear_velocity;
+    if angular_velocity.length_squared() == 0.0 {
+        return (0.0, transform.translation);
+    }
+    // Only the velocity perpendicular to the rotation axis is bent into a circle
+    let center_offset = angular_velocity.cross(linear_velocity) / angular_velocity.length_squared();
 
-    turn_circle_center.rotate_around(transform.translation, transform.rotation);
-    (turn_radius, turn_circle_center.translation)
+    (
+        center_offset.length(),
+        transform.translation + center_offset,
+    )
 }
-// #[derive(Component)]
-// pub struct DebugCube;
-pub fn apply_velocity(
-    // mut commands: Commands,
-    time: Res<Time>,
-    mut vessels: Query<(&mut Transform, &mut VelocityVector)>,
-    // mut debug_cube_query: Query<
-    //     (Entity, &mut Transform),
-    //     (With<DebugCube>, Without<VelocityVector>),
-    // >,
-    // mut meshes: ResMut<Assets<Mesh>>,
-    // mut materials: ResMut<Assets<StandardMaterial>>,
-) {
-    for (mut vessel_transform, mut vessel_velocity) in vessels.iter_mut() {
-        // let mut debug_cube_instance = debug_cube_query.get_single_mut();
-        if vessel_velocity.angular_velocity == Vec3::ZERO {
-            // if let Ok((debug_cube_entity, _debug_cube)) = debug_cube_instance {
-            //     commands.entity(debug_cube_entity).despawn();
-
-            // }
-            let x_direction = vessel_transform.local_x();
-            let y_direction = vessel_transform.local_y();
-            let z_direction = vessel_transform.local_z();
-
-            let global_velocity_vector = x_direction * vessel_velocity.linear_velocity.x
-                + y_direction * vessel_velocity.linear_velocity.y
-                + z_direction * vessel_velocity.linear_velocity.z;
-
-            vessel_transform.translation += global_velocity_vector * time.delta_seconds();
-        } else {
-            let (turn_radius, turn_circle_center) =
-                calculate_turn_circle_center(&vessel_transform, &vessel_velocity);
-            vessel_velocity.turn_radius = turn_radius;
-            vessel_velocity.turn_circle_center = turn_circle_center;
-            if turn_radius == 0.0 {
-                vessel_transform
-                    .rotate_local_y(vessel_velocity.angular_velocity.y * time.delta_seconds());
-            } else {
-                println!("Turn Radius: {:?}", turn_radius);
+/// Mirrors the physics body's world space velocity into the vessel's local frame
+pub fn read_velocity(mut vessels: Query<(&Transform, &Velocity, &mut VelocityVector)>) {
+    for (vessel_transform, velocity, mut vessel_velocity) in vessels.iter_mut() {
+        let inverse_rotation = vessel_transform.rotation.inverse();
+        vessel_velocity.linear_velocity = inverse_rotation * velocity.linvel;
+        vessel_velocity.angular_velocity = inverse_rotation * velocity.angvel;
 
-                // match debug_cube_instance {
-                //     Ok((_, mut debug_cube_transform)) => {
-                //         debug_cube_transform.translation = turn_circle_center;
-                //     }
-                //     Err(_) => {
-                //         commands.spawn((
-                //             PbrBundle {
-                //                 mesh: meshes.add(Cuboid::default()),
-
-                //                 material: materials.add(StandardMaterial {
-                //                     emissive: LinearRgba::rgb(2.0, 13.99, 5.32),
-                //                     ..default()
-                //                 }),
-                //                 transform: Transform::from_translation(turn_circle_center),
-                //                 ..default()
-                //             },
-                //             DebugCube,
-                //         ));
-                //     }
-                // }
-                let turn_circle_circumference = 2.0 * PI * turn_radius;
-                let path_travelled = vessel_velocity.linear_velocity.x * time.delta_seconds();
-                vessel_transform.rotate_around(
-                    turn_circle_center,
-                    Quat::from_rotation_y(2.0 * PI * (path_travelled / turn_circle_circumference)),
-                )
-            }
-        }
+        let (turn_radius, turn_circle_center) =
+            calculate_turn_circle_center(vessel_transform, &vessel_velocity);
+        vessel_velocity.turn_radius = turn_radius;
+        vessel_velocity.turn_circle_center = turn_circle_center;
     }
 }
@@ -0,0 +1,210 @@
+use std::f32::consts::SQRT_2;
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use bevy_rapier3d::prelude::Collider;
+
+use crate::player::control::Helm;
+
+use super::{
+    autopilot::{fly_orders, signed_command},
+    movements::{MovementEvent, MovementType},
+    vessels::{VesselDefinition, VesselID},
+};
+
+/// Distance kept from obstacles on top of the vessel's own size
+const OBSTACLE_MARGIN: f32 = 50.0;
+/// How often a path may split around another obstacle, bounding the planning effort
+const MAX_DETOUR_DEPTH: u32 = 4;
+/// Distance within which vessels steer apart, as a multiple of their combined collider sizes
+const AVOIDANCE_RANGE: f32 = 1.5;
+
+/// A sphere vessels plan their paths around, such as a planet or the volume of a dense asteroid field
+#[derive(Component)]
+pub struct Obstacle {
+    pub radius: f32,
+}
+impl Obstacle {
+    /// The sphere enclosing `collider` at the scale of the `transform` it is spawned with
+    pub fn enclosing(collider: &Collider, transform: &Transform) -> Self {
+        Obstacle {
+            radius: collider.raw.compute_local_bounding_sphere().radius
+                * transform.scale.max_element(),
+        }
+    }
+}
+
+/// Plans paths around the obstacles in the system
+#[derive(SystemParam)]
+pub struct Navigation<'w, 's> {
+    obstacles: Query<'w, 's, (&'static GlobalTransform, &'static Obstacle)>,
+}
+impl<'w, 's> Navigation<'w, 's> {
+    /// Waypoints from `from` to `to` keeping `clearance` away from every obstacle, the last one is `to`
+    /// or the nearest point outside the obstacle it lies in
+    pub fn path(&self, from: Vec3, to: Vec3, clearance: f32) -> Vec<Vec3> {
+        let obstacles: Vec<(Vec3, f32)> = self
+            .obstacles
+            .iter()
+            .map(|(transform, obstacle)| {
+                (
+                    transform.translation(),
+                    obstacle.radius + clearance + OBSTACLE_MARGIN,
+                )
+            })
+            // There is no way around an obstacle the vessel is already in, only out of it
+            .filter(|(centre, radius)| centre.distance(from) > *radius)
+            .collect();
+        let destination = obstacles.iter().fold(to, |destination, (centre, radius)| {
+            let offset = destination - *centre;
+            if offset.length() < *radius {
+                *centre + offset.try_normalize().unwrap_or(Vec3::Y) * *radius
+            } else {
+                destination
+            }
+        });
+        route(from, destination, &obstacles, MAX_DETOUR_DEPTH)
+    }
+}
+
+/// Waypoints after `from` up to `to`, detouring around the first obstacle on the way and then planning
+/// both legs of the detour again
+fn route(from: Vec3, to: Vec3, obstacles: &[(Vec3, f32)], depth: u32) -> Vec<Vec3> {
+    if depth == 0 {
+        return vec![to];
+    }
+    let leg = to - from;
+    let blocking = obstacles
+        .iter()
+        .filter_map(|(centre, radius)| {
+            let along = if leg.length_squared() > 0.0 {
+                ((*centre - from).dot(leg) / leg.length_squared()).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            let closest = from + leg * along;
+            (closest.distance(*centre) < *radius).then_some((along, *centre, *radius, closest))
+        })
+        .min_by(|(along_a, ..), (along_b, ..)| along_a.total_cmp(along_b));
+    let Some((_, centre, radius, closest)) = blocking else {
+        return vec![to];
+    };
+    let outwards = (closest - centre)
+        .try_normalize()
+        .unwrap_or_else(|| leg.normalize().any_orthonormal_vector());
+    // Legs through this corner pass the sphere about tangentially
+    let detour = centre + outwards * radius * SQRT_2;
+    let mut path = route(from, detour, obstacles, depth - 1);
+    path.extend(route(detour, to, obstacles, depth - 1));
+    path
+}
+
+pub struct NavigationPlugin;
+impl Plugin for NavigationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, avoid_vessels.after(fly_orders));
+    }
+}
+
+/// Strafes vessels away from others flying too close, so fleets arriving together don't pile up.
+/// Hand-flown vessels are left to the player.
+fn avoid_vessels(
+    helm: Helm,
+    mut movement_events: EventWriter<MovementEvent>,
+    vessels: Query<(Entity, &VesselID, &Transform, &VesselDefinition)>,
+) {
+    for (entity, vessel_id, transform, vessel_definition) in vessels.iter() {
+        if helm.is_hand_flown(vessel_id) {
+            continue;
+        }
+        let size = vessel_definition.collider_half_extents.length();
+        let push = vessels
+            .iter()
+            .filter(|(other, _, _, _)| *other != entity)
+            .fold(
+                Vec3::ZERO,
+                |push, (_, _, other_transform, other_definition)| {
+                    let offset = transform.translation - other_transform.translation;
+                    let range =
+                        (size + other_definition.collider_half_extents.length()) * AVOIDANCE_RANGE;
+                    let distance = offset.length();
+                    if distance >= range || distance == 0.0 {
+                        return push;
+                    }
+                    // Push harder the closer the other vessel is
+                    push + offset / distance * (1.0 - distance / range)
+                },
+            );
+        if push == Vec3::ZERO {
+            continue;
+        }
+        let local_push = (transform.rotation.inverse() * push).clamp(Vec3::NEG_ONE, Vec3::ONE);
+        for (movement_type, magnitude) in [
+            signed_command(local_push.x, MovementType::Forward, MovementType::Backward),
+            signed_command(
+                local_push.y,
+                MovementType::StrafeUp,
+                MovementType::StrafeDown,
+            ),
+            signed_command(
+                local_push.z,
+                MovementType::StrafeRight,
+                MovementType::StrafeLeft,
+            ),
+        ] {
+            if magnitude > 0.0 {
+                movement_events.send(MovementEvent {
+                    movement_type,
+                    vessel_id: vessel_id.clone(),
+                    magnitude,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Closest distance between `centre` and the segment from `from` to `to`
+    fn segment_distance(from: Vec3, to: Vec3, centre: Vec3) -> f32 {
+        let leg = to - from;
+        let along = ((centre - from).dot(leg) / leg.length_squared()).clamp(0.0, 1.0);
+        (from + leg * along).distance(centre)
+    }
+
+    #[test]
+    fn route_clear_path_goes_straight() {
+        let obstacles = [(Vec3::new(0.0, 500.0, 0.0), 100.0)];
+        let to = Vec3::new(1000.0, 0.0, 0.0);
+        assert_eq!(
+            route(Vec3::ZERO, to, &obstacles, MAX_DETOUR_DEPTH),
+            vec![to]
+        );
+    }
+
+    #[test]
+    fn route_detours_around_blocking_obstacle() {
+        let from = Vec3::new(-500.0, 10.0, 0.0);
+        let to = Vec3::new(500.0, 0.0, 0.0);
+        let obstacles = [(Vec3::ZERO, 100.0)];
+        let path = route(from, to, &obstacles, MAX_DETOUR_DEPTH);
+        assert!(path.len() > 1);
+        assert_eq!(path.last(), Some(&to));
+        let mut leg_start = from;
+        for waypoint in path {
+            assert!(segment_distance(leg_start, waypoint, Vec3::ZERO) >= 100.0);
+            leg_start = waypoint;
+        }
+    }
+
+    #[test]
+    fn route_without_depth_goes_straight() {
+        let to = Vec3::new(500.0, 0.0, 0.0);
+        let obstacles = [(Vec3::ZERO, 100.0)];
+        assert_eq!(
+            route(Vec3::new(-500.0, 0.0, 0.0), to, &obstacles, 0),
+            vec![to]
+        );
+    }
+}
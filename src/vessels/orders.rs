@@ -0,0 +1,108 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use super::{
+    autopilot::fly_orders,
+    targeting::{TargetLock, TargetingQuery},
+    vessels::VesselID,
+    weapons::Target,
+};
+
+/// Something a vessel is told to do instead of being flown by hand
+#[derive(Clone, Debug)]
+pub enum Order {
+    /// Fly to a point and come to a stop there
+    MoveTo(Vec3),
+    /// Close to within `distance` of a vessel and stop
+    Approach { target: Entity, distance: f32 },
+    /// Circle a vessel at `radius` around its up axis
+    Orbit { target: Entity, radius: f32 },
+    /// Fly alongside a vessel at its speed and heading
+    MatchVelocity(Entity),
+    /// Close in on a vessel, lock on to it and fire on it
+    Attack(Entity),
+    /// Keep station behind a vessel
+    Follow(Entity),
+    /// Fly from waypoint to waypoint and start over after the last, `next` is the one being flown to
+    Patrol { waypoints: Vec<Vec3>, next: usize },
+    /// Come to a stop and stay put until given another order
+    Hold,
+}
+impl Order {
+    /// The vessel the order is carried out against, if any
+    pub fn target(&self) -> Option<Entity> {
+        match self {
+            Order::Approach { target, .. }
+            | Order::Orbit { target, .. }
+            | Order::MatchVelocity(target)
+            | Order::Attack(target)
+            | Order::Follow(target) => Some(*target),
+            Order::MoveTo(_) | Order::Patrol { .. } | Order::Hold => None,
+        }
+    }
+}
+
+#[derive(Event)]
+pub struct OrderEvent {
+    pub vessel_id: VesselID,
+    pub order: Order,
+    /// Carry the order out after the ones already queued instead of replacing them
+    pub queue: bool,
+}
+
+/// Orders a vessel's autopilot carries out one after the other, the front one is underway
+#[derive(Component, Default)]
+pub struct OrderQueue(pub VecDeque<Order>);
+
+pub struct OrdersPlugin;
+impl Plugin for OrdersPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<OrderEvent>().add_systems(
+            Update,
+            (receive_orders, lock_attack_targets, fly_orders).chain(),
+        );
+    }
+}
+
+fn receive_orders(
+    mut order_events: EventReader<OrderEvent>,
+    mut vessels: Query<(&VesselID, &mut OrderQueue)>,
+) {
+    for order_event in order_events.read() {
+        let Some((_, mut order_queue)) = vessels
+            .iter_mut()
+            .find(|(vessel_id, _)| **vessel_id == order_event.vessel_id)
+        else {
+            continue;
+        };
+        if !order_event.queue {
+            order_queue.0.clear();
+        }
+        order_queue.0.push_back(order_event.order.clone());
+    }
+}
+
+/// Starts a lock on the target of an attack order once it is underway, unless it is locked already
+fn lock_attack_targets(mut commands: Commands, vessels: Query<(&OrderQueue, TargetingQuery)>) {
+    for (order_queue, vessel) in vessels.iter() {
+        let Some(Order::Attack(attacked)) = order_queue.0.front() else {
+            continue;
+        };
+        if vessel.target.is_some_and(|target| target.0 == *attacked)
+            || vessel.lock.is_some_and(|lock| lock.candidate == *attacked)
+        {
+            continue;
+        }
+        commands
+            .entity(vessel.entity)
+            .remove::<Target>()
+            .try_insert(TargetLock {
+                candidate: *attacked,
+                progress: Timer::from_seconds(
+                    vessel.vessel_definition.sensors.lock_time,
+                    TimerMode::Once,
+                ),
+            });
+    }
+}
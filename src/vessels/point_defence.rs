@@ -0,0 +1,170 @@
+use bevy::{prelude::*, utils::HashMap};
+use bevy_rapier3d::prelude::Velocity;
+
+use crate::player::player::Player;
+
+use super::{
+    missiles::Guided,
+    turrets::Turret,
+    vessels::{VesselClass, VesselDefinition, VesselID},
+    weapon_groups::FireMode,
+    weapons::{Projectile, WeaponStats, WeaponsFireEvent, WeaponsType},
+};
+
+/// Hostile threats closing in on a vessel, soonest impact first, with the point to aim at. Threats are
+/// given by the player they belong to, their position and their velocity.
+fn incoming_threats(
+    vessel_id: &VesselID,
+    vessel_transform: &Transform,
+    vessel_velocity: &Velocity,
+    point_defence_stats: &WeaponStats,
+    threats: &[(&Player, Vec3, Vec3)],
+) -> Vec<(f32, Vec3)> {
+    let mut incoming: Vec<(f32, Vec3)> = threats
+        .iter()
+        .filter(|(player, _, _)| **player != vessel_id.player)
+        .filter_map(|(_, threat_position, threat_velocity)| {
+            let relative_position = *threat_position - vessel_transform.translation;
+            let relative_velocity = *threat_velocity - vessel_velocity.linvel;
+            let distance = relative_position.length();
+            // A threat on top of the mount has no direction to close in from
+            if distance == 0.0 {
+                return None;
+            }
+            let closing_speed = -relative_position.dot(relative_velocity) / distance;
+            if distance > point_defence_stats.range || closing_speed <= 0.0 {
+                return None;
+            }
+            // Lead the threat by the flight time of a point-defence round
+            let intercept_point = *threat_position
+                + relative_velocity * distance / point_defence_stats.velocity.length();
+            Some((distance / closing_speed, intercept_point))
+        })
+        .collect();
+    incoming.sort_by(|(time_a, _), (time_b, _)| time_a.total_cmp(time_b));
+    incoming
+}
+
+/// Spreads the point-defence turrets of every vessel over the incoming missiles and fighters, most urgent
+/// first
+pub fn assign_point_defence(
+    vessels: Query<(&VesselID, &VesselDefinition, &Transform, &Velocity)>,
+    missiles: Query<(&Transform, &Velocity, &Projectile), With<Guided>>,
+    mut turrets: Query<(&mut Turret, &Parent)>,
+) {
+    let fighters = vessels
+        .iter()
+        .filter(|(_, vessel_definition, _, _)| {
+            matches!(vessel_definition.class, VesselClass::Fighter)
+        })
+        .map(|(vessel_id, _, transform, velocity)| {
+            (&vessel_id.player, transform.translation, velocity.linvel)
+        });
+    let threats: Vec<(&Player, Vec3, Vec3)> = missiles
+        .iter()
+        .map(|(transform, velocity, projectile)| {
+            (
+                &projectile.vessel_id.player,
+                transform.translation,
+                velocity.linvel,
+            )
+        })
+        .chain(fighters)
+        .collect();
+    let mut turret_counts: HashMap<Entity, usize> = HashMap::new();
+    for (mut turret, parent) in turrets.iter_mut() {
+        if turret.weapons_type != WeaponsType::PointDefence {
+            continue;
+        }
+        let Ok((vessel_id, vessel_definition, vessel_transform, vessel_velocity)) =
+            vessels.get(parent.get())
+        else {
+            continue;
+        };
+        // Engagement range and lead come from the vessel's point-defence stats
+        let Some(point_defence_stats) = vessel_definition.weapon_stats(&WeaponsType::PointDefence)
+        else {
+            continue;
+        };
+        let incoming = incoming_threats(
+            vessel_id,
+            vessel_transform,
+            vessel_velocity,
+            point_defence_stats,
+            &threats,
+        );
+        let turret_count = turret_counts.entry(parent.get()).or_insert(0);
+        turret.intercept_point =
+            (!incoming.is_empty()).then(|| incoming[*turret_count % incoming.len()].1);
+        *turret_count += 1;
+    }
+}
+
+/// Fires the point defence of every vessel that has a turret on an incoming threat
+pub fn fire_point_defence(
+    mut weapons_fire_event: EventWriter<WeaponsFireEvent>,
+    vessels: Query<&VesselID>,
+    turrets: Query<(&Turret, &Parent)>,
+) {
+    let mut engaging_vessels: Vec<Entity> = turrets
+        .iter()
+        .filter(|(turret, _)| {
+            turret.weapons_type == WeaponsType::PointDefence
+                && turret.intercept_point.is_some()
+                && turret.on_target
+        })
+        .map(|(_, parent)| parent.get())
+        .collect();
+    engaging_vessels.sort_unstable();
+    engaging_vessels.dedup();
+    for vessel_entity in engaging_vessels {
+        let Ok(vessel_id) = vessels.get(vessel_entity) else {
+            continue;
+        };
+        weapons_fire_event.send(WeaponsFireEvent {
+            vessel_id: vessel_id.clone(),
+            weapons_type: WeaponsType::PointDefence,
+            fire_mode: FireMode::All,
+            aim_at_target: false,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point_defence_stats() -> WeaponStats {
+        WeaponStats {
+            weapons_type: WeaponsType::PointDefence,
+            color: LinearRgba::WHITE,
+            velocity: Vec3::new(800.0, 0.0, 0.0),
+            damage: 1.0,
+            range: 1000.0,
+            guidance: None,
+            beam: None,
+        }
+    }
+
+    #[test]
+    fn incoming_threats_skips_threat_at_mount() {
+        let vessel_id = VesselID {
+            player: Player::Host,
+            id: 0,
+        };
+        let threats = [
+            (&Player::AI(1), Vec3::ZERO, Vec3::X * 100.0),
+            (&Player::AI(1), Vec3::X * 500.0, Vec3::NEG_X * 100.0),
+        ];
+        let incoming = incoming_threats(
+            &vessel_id,
+            &Transform::IDENTITY,
+            &Velocity::zero(),
+            &point_defence_stats(),
+            &threats,
+        );
+        assert_eq!(incoming.len(), 1);
+        assert!((incoming[0].0 - 5.0).abs() < 1e-3);
+        assert!(incoming[0].1.is_finite());
+    }
+}
@@ -1,26 +1,112 @@
-use crate::{player::player::Player, vessels::vessels::VesselDefinition};
-use bevy::prelude::*;
+use crate::vessels::vessels::VesselDefinition;
+use bevy::{asset::LoadState, prelude::*};
+use bevy_rapier3d::prelude::*;
 
-use super::{movements::VelocityVector, vessels::VesselID};
+use super::{
+    damage::{ram_threshold, Hull},
+    defences::{Armour, Shields},
+    movements::{FlightAssist, VelocityVector},
+    orders::OrderQueue,
+    subsystems::Subsystems,
+    vessels::VesselID,
+    weapons::WeaponsState,
+};
 
+/// A vessel waiting for its definition asset to finish loading
+#[derive(Component)]
+pub struct VesselSpawn {
+    vessel_definition: Handle<VesselDefinition>,
+    vessel_id: VesselID,
+    velocity_vector: VelocityVector,
+}
+
+/// Queues a vessel, it is completed by [`complete_vessel_spawns`] once its definition is loaded
 pub fn spawn_vessel(
     commands: &mut Commands,
-    asset_server: &Res<AssetServer>,
-    vessel_definition: &VesselDefinition,
+    vessel_definition: Handle<VesselDefinition>,
     vessel_id: VesselID,
     velocity_vector: VelocityVector,
+) -> Entity {
+    commands
+        .spawn((
+            VesselSpawn {
+                vessel_definition,
+                vessel_id,
+                velocity_vector,
+            },
+            SpatialBundle::default(),
+        ))
+        .id()
+}
+
+pub fn complete_vessel_spawns(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    vessel_definitions: Res<Assets<VesselDefinition>>,
+    mut vessel_spawns: Query<(Entity, &mut VesselSpawn, &Transform)>,
 ) {
-    commands.spawn((
-        SceneBundle {
-            scene: asset_server.load(
-                GltfAssetLabel::Scene(0).from_asset(vessel_definition.model_path.clone()),
-                // GltfAssetLabel::Scene(0).from_asset(),
+    for (entity, mut vessel_spawn, transform) in vessel_spawns.iter_mut() {
+        let Some(vessel_definition) = vessel_definitions.get(&vessel_spawn.vessel_definition)
+        else {
+            // A definition that failed to load or validate never arrives, so the vessel is given up on
+            if let LoadState::Failed(error) =
+                asset_server.load_state(&vessel_spawn.vessel_definition)
+            {
+                error!(
+                    "Could not spawn vessel {:?}: {}",
+                    vessel_spawn.vessel_id, error
+                );
+                commands.entity(entity).despawn_recursive();
+            }
+            continue;
+        };
+        let velocity_vector = std::mem::take(&mut vessel_spawn.velocity_vector);
+        commands.entity(entity).remove::<VesselSpawn>().insert((
+            SceneBundle {
+                scene: asset_server.load(
+                    GltfAssetLabel::Scene(0).from_asset(vessel_definition.model_path.clone()),
+                    // GltfAssetLabel::Scene(0).from_asset(),
+                ),
+                // transform: Transform::from_scale(Vec3::splat(0.01)),
+                transform: *transform,
+                ..default()
+            },
+            vessel_spawn.vessel_id.clone(),
+            vessel_definition.clone(),
+            vessel_spawn.vessel_definition.clone(),
+            FlightAssist::default(),
+            RigidBody::Dynamic,
+            Collider::cuboid(
+                vessel_definition.collider_half_extents.x,
+                vessel_definition.collider_half_extents.y,
+                vessel_definition.collider_half_extents.z,
+            ),
+            ColliderMassProperties::Mass(vessel_definition.mass),
+            ReadMassProperties::default(),
+            ExternalForce::default(),
+            Velocity {
+                linvel: transform.rotation * velocity_vector.linear_velocity,
+                angvel: transform.rotation * velocity_vector.angular_velocity,
+            },
+            ActiveEvents::COLLISION_EVENTS | ActiveEvents::CONTACT_FORCE_EVENTS,
+            ram_threshold(vessel_definition.mass),
+            velocity_vector,
+            (
+                Hull::new(vessel_definition.hull),
+                WeaponsState::new(vessel_definition),
+                Subsystems::new(vessel_definition),
+                OrderQueue::default(),
             ),
-            // transform: Transform::from_scale(Vec3::splat(0.01)),
-            ..default()
-        },
-        vessel_id,
-        vessel_definition.clone(),
-        velocity_vector,
-    ));
+        ));
+        if let Some(shield_properties) = &vessel_definition.shields {
+            commands
+                .entity(entity)
+                .insert(Shields::new(shield_properties));
+        }
+        if let Some(armour_properties) = &vessel_definition.armour {
+            commands
+                .entity(entity)
+                .insert(Armour::new(armour_properties));
+        }
+    }
 }
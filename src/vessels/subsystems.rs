@@ -0,0 +1,148 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use super::{vessels::VesselDefinition, weapons::WeaponsType};
+
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize)]
+pub enum SubsystemType {
+    /// Thrusters, losing them reduces linear and angular acceleration
+    Engines,
+    /// Feeds the hardpoints of a weapons type within its radius, or all of them if it covers none.
+    /// Damage slows the rate of fire of the mounts it feeds, they go silent once it is destroyed.
+    Weapons(WeaponsType),
+    /// Losing sensors shortens the range targets can be selected and held at
+    Sensors,
+    /// Losing the generator slows shield regeneration
+    ShieldGenerator,
+}
+
+/// A part of a vessel that can be shot out on its own
+#[derive(Clone, Deserialize)]
+pub struct SubsystemProperties {
+    pub subsystem_type: SubsystemType,
+    /// Position on the model relative to the vessel, like hardpoint transforms
+    pub transform: Transform,
+    /// Distance from `transform` within which hits damage the subsystem
+    pub radius: f32,
+    pub integrity: f32,
+}
+
+/// Remaining integrity of every subsystem, in the order of [`VesselDefinition::subsystems`]
+#[derive(Component)]
+pub struct Subsystems {
+    pub integrity: Vec<f32>,
+}
+impl Subsystems {
+    pub fn new(vessel_definition: &VesselDefinition) -> Self {
+        Subsystems {
+            integrity: vessel_definition
+                .subsystems
+                .iter()
+                .map(|subsystem| subsystem.integrity)
+                .collect(),
+        }
+    }
+    /// Subsystems of a reloaded definition, those that kept their place and type keep the integrity left
+    /// up to the new maximum
+    pub fn refitted(
+        &self,
+        previous_definition: &VesselDefinition,
+        vessel_definition: &VesselDefinition,
+    ) -> Self {
+        Subsystems {
+            integrity: vessel_definition
+                .subsystems
+                .iter()
+                .enumerate()
+                .map(|(index, subsystem)| {
+                    previous_definition
+                        .subsystems
+                        .get(index)
+                        .filter(|previous| previous.subsystem_type == subsystem.subsystem_type)
+                        .and_then(|_| self.integrity.get(index))
+                        .map_or(subsystem.integrity, |integrity| {
+                            integrity.min(subsystem.integrity)
+                        })
+                })
+                .collect(),
+        }
+    }
+
+    /// Fraction of the combined integrity left in all subsystems of a type, vessels without such subsystems work at full efficiency
+    pub fn efficiency(
+        &self,
+        vessel_definition: &VesselDefinition,
+        subsystem_type: &SubsystemType,
+    ) -> f32 {
+        self.combined_efficiency(vessel_definition, |subsystem| {
+            subsystem.subsystem_type == *subsystem_type
+        })
+    }
+
+    /// Efficiency of the weapons subsystems feeding a hardpoint of `weapons_type` at `position`, given in
+    /// the vessel's frame. The subsystems around the hardpoint feed it, without any it depends on all of its type.
+    pub fn hardpoint_efficiency(
+        &self,
+        vessel_definition: &VesselDefinition,
+        weapons_type: &WeaponsType,
+        position: Vec3,
+    ) -> f32 {
+        let subsystem_type = SubsystemType::Weapons(weapons_type.clone());
+        let covering = |subsystem: &SubsystemProperties| {
+            subsystem.subsystem_type == subsystem_type
+                && subsystem.transform.translation.distance(position) <= subsystem.radius
+        };
+        if vessel_definition.subsystems.iter().any(covering) {
+            self.combined_efficiency(vessel_definition, covering)
+        } else {
+            self.efficiency(vessel_definition, &subsystem_type)
+        }
+    }
+
+    /// Fraction of the combined integrity left in the subsystems `included`, full efficiency without any
+    fn combined_efficiency(
+        &self,
+        vessel_definition: &VesselDefinition,
+        included: impl Fn(&SubsystemProperties) -> bool,
+    ) -> f32 {
+        let (integrity, max_integrity) = vessel_definition
+            .subsystems
+            .iter()
+            .zip(self.integrity.iter())
+            .filter(|(subsystem, _)| included(subsystem))
+            .fold(
+                (0.0, 0.0),
+                |(integrity, max_integrity), (subsystem, left)| {
+                    (integrity + left, max_integrity + subsystem.integrity)
+                },
+            );
+        if max_integrity <= 0.0 {
+            return 1.0;
+        }
+        integrity / max_integrity
+    }
+
+    /// Damages every subsystem around `position`, given in the vessel's frame
+    pub fn damage(&mut self, vessel_definition: &VesselDefinition, position: Vec3, damage: f32) {
+        for (subsystem, integrity) in vessel_definition
+            .subsystems
+            .iter()
+            .zip(self.integrity.iter_mut())
+        {
+            if subsystem.transform.translation.distance(position) <= subsystem.radius {
+                *integrity = (*integrity - damage).max(0.0);
+            }
+        }
+    }
+}
+
+/// Efficiency of a subsystem type on a vessel that may not track subsystems at all
+pub fn subsystem_efficiency(
+    subsystems: Option<&Subsystems>,
+    vessel_definition: &VesselDefinition,
+    subsystem_type: &SubsystemType,
+) -> f32 {
+    subsystems.map_or(1.0, |subsystems| {
+        subsystems.efficiency(vessel_definition, subsystem_type)
+    })
+}
@@ -0,0 +1,183 @@
+use bevy::{ecs::query::QueryData, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    subsystems::{subsystem_efficiency, SubsystemType, Subsystems},
+    vessels::{VesselDefinition, VesselID},
+    weapons::Target,
+};
+
+pub struct TargetingPlugin;
+impl Plugin for TargetingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<TargetingEvent>().add_systems(
+            Update,
+            (
+                select_target,
+                acquire_lock.after(select_target),
+                lose_lock.after(acquire_lock),
+            ),
+        );
+    }
+}
+
+#[derive(Clone, Deserialize)]
+pub struct SensorProperties {
+    /// Distance up to which targets can be selected and held
+    pub range: f32,
+    /// Seconds a target has to stay in range before it is locked
+    pub lock_time: f32,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum TargetingType {
+    /// Select the closest hostile vessel in sensor range
+    Nearest,
+    /// Cycle to the next further hostile vessel in sensor range
+    Next,
+    Clear,
+}
+#[derive(Event)]
+pub struct TargetingEvent {
+    pub targeting_type: TargetingType,
+    pub vessel_id: VesselID,
+}
+
+/// A selected target the vessel is still locking on to
+#[derive(Component)]
+pub struct TargetLock {
+    pub candidate: Entity,
+    pub progress: Timer,
+}
+
+/// A vessel's sensors together with the target they hold or are locking on to
+#[derive(QueryData)]
+pub struct TargetingQuery {
+    pub entity: Entity,
+    pub vessel_id: &'static VesselID,
+    pub transform: &'static Transform,
+    pub vessel_definition: &'static VesselDefinition,
+    pub subsystems: Option<&'static Subsystems>,
+    pub target: Option<&'static Target>,
+    pub lock: Option<&'static TargetLock>,
+}
+
+/// Hostile vessels within sensor range sorted by distance, closest first
+pub fn hostiles_in_range<'a>(
+    vessel_id: &VesselID,
+    vessel_position: Vec3,
+    range: f32,
+    vessels: impl Iterator<Item = (Entity, &'a VesselID, &'a Transform)>,
+) -> Vec<(Entity, f32)> {
+    let mut hostiles: Vec<(Entity, f32)> = vessels
+        .filter(|(_, other_vessel_id, _)| other_vessel_id.player != vessel_id.player)
+        .map(|(entity, _, transform)| (entity, transform.translation.distance(vessel_position)))
+        .filter(|(_, distance)| *distance <= range)
+        .collect();
+    hostiles.sort_by(|(_, distance_a), (_, distance_b)| distance_a.total_cmp(distance_b));
+    hostiles
+}
+
+/// Sensor range shrinks with the integrity left in the sensor subsystems
+pub fn sensor_range(vessel_definition: &VesselDefinition, subsystems: Option<&Subsystems>) -> f32 {
+    vessel_definition.sensors.range
+        * subsystem_efficiency(subsystems, vessel_definition, &SubsystemType::Sensors)
+}
+
+fn select_target(
+    mut commands: Commands,
+    mut targeting_events: EventReader<TargetingEvent>,
+    vessels: Query<TargetingQuery>,
+) {
+    for targeting_event in targeting_events.read() {
+        let Some(vessel) = vessels
+            .iter()
+            .find(|vessel| *vessel.vessel_id == targeting_event.vessel_id)
+        else {
+            continue;
+        };
+        let hostiles = hostiles_in_range(
+            vessel.vessel_id,
+            vessel.transform.translation,
+            sensor_range(vessel.vessel_definition, vessel.subsystems),
+            vessels
+                .iter()
+                .map(|other| (other.entity, other.vessel_id, other.transform)),
+        );
+        let current_target = vessel
+            .target
+            .map(|target| target.0)
+            .or(vessel.lock.map(|lock| lock.candidate));
+        let new_target = match targeting_event.targeting_type {
+            TargetingType::Nearest => hostiles.first().map(|(entity, _)| *entity),
+            TargetingType::Next => {
+                let next_index = current_target
+                    .and_then(|current_target| {
+                        hostiles
+                            .iter()
+                            .position(|(entity, _)| *entity == current_target)
+                    })
+                    .map_or(0, |index| (index + 1) % hostiles.len());
+                hostiles.get(next_index).map(|(entity, _)| *entity)
+            }
+            TargetingType::Clear => None,
+        };
+
+        let mut vessel_commands = commands.entity(vessel.entity);
+        match new_target {
+            Some(new_target) if Some(new_target) != current_target => {
+                vessel_commands.remove::<Target>().try_insert(TargetLock {
+                    candidate: new_target,
+                    progress: Timer::from_seconds(
+                        vessel.vessel_definition.sensors.lock_time,
+                        TimerMode::Once,
+                    ),
+                });
+            }
+            Some(_) => {}
+            None => {
+                vessel_commands.remove::<(Target, TargetLock)>();
+            }
+        }
+    }
+}
+
+fn acquire_lock(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut locking_vessels: Query<(Entity, &mut TargetLock)>,
+) {
+    for (vessel_entity, mut lock) in locking_vessels.iter_mut() {
+        if lock.progress.tick(time.delta()).just_finished() {
+            commands
+                .entity(vessel_entity)
+                .remove::<TargetLock>()
+                .try_insert(Target(lock.candidate));
+        }
+    }
+}
+
+/// Drops targets and lock attempts on vessels that left sensor range or no longer exist
+fn lose_lock(
+    mut commands: Commands,
+    vessels: Query<TargetingQuery>,
+    positions: Query<&Transform, With<VesselID>>,
+) {
+    for vessel in vessels.iter() {
+        let range = sensor_range(vessel.vessel_definition, vessel.subsystems);
+        let in_range = |target_entity: Entity| {
+            positions.get(target_entity).is_ok_and(|target_transform| {
+                target_transform
+                    .translation
+                    .distance(vessel.transform.translation)
+                    <= range
+            })
+        };
+        if vessel.target.is_some_and(|target| !in_range(target.0)) {
+            commands.entity(vessel.entity).remove::<Target>();
+        }
+        if vessel.lock.is_some_and(|lock| !in_range(lock.candidate)) {
+            commands.entity(vessel.entity).remove::<TargetLock>();
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use bevy::{ecs::query::QueryData, prelude::*};
+use serde::Deserialize;
+
+use super::{
+    gunnery::lead_point,
+    movements::VelocityVector,
+    vessels::{VesselDefinition, VesselID},
+    weapons::{Target, WeaponsState, WeaponsType},
+};
+
+/// Largest angle in degrees between barrel and target at which a turret opens fire
+const FIRING_TOLERANCE: f32 = 2.0;
+const PI_OVER_180: f32 = std::f32::consts::PI / 180.0;
+
+/// Traverse limits of a turreted hardpoint, angles in degrees relative to the hardpoint transform
+#[derive(Clone, Deserialize)]
+pub struct TurretProperties {
+    /// Rotation around the mount's local y axis, positive turns towards port
+    pub yaw_limits: Vec2,
+    /// Rotation around the mount's local z axis, positive elevates the barrel
+    pub pitch_limits: Vec2,
+    /// Degrees per second the turret turns on either axis
+    pub traverse_speed: f32,
+}
+
+/// Turret child entity of a vessel, it points its local x axis along the barrel
+#[derive(Component)]
+pub struct Turret {
+    pub weapons_type: WeaponsType,
+    pub hardpoint_index: usize,
+    pub yaw: f32,
+    pub pitch: f32,
+    /// Whether the locked target is inside the firing arc and the barrel points at it
+    pub on_target: bool,
+    /// Point-defence turrets aim here instead of at the locked target
+    pub intercept_point: Option<Vec3>,
+}
+
+/// Gives every turreted hardpoint of a new vessel a visible turret entity
+pub fn spawn_turrets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    vessels: Query<(Entity, &VesselDefinition), Added<WeaponsState>>,
+) {
+    for (vessel_entity, vessel_definition) in vessels.iter() {
+        for (weapons_type, hardpoints) in vessel_definition.hardpoints.iter() {
+            for (hardpoint_index, hardpoint) in hardpoints.iter().enumerate() {
+                if hardpoint.turret.is_none() {
+                    continue;
+                }
+                let turret = commands
+                    .spawn((
+                        PbrBundle {
+                            mesh: meshes.add(Cuboid::new(1.5, 0.3, 0.3)),
+                            material: materials.add(StandardMaterial {
+                                base_color: Color::srgb(0.4, 0.4, 0.45),
+                                ..default()
+                            }),
+                            transform: hardpoint.transform,
+                            ..default()
+                        },
+                        Turret {
+                            weapons_type: weapons_type.clone(),
+                            hardpoint_index,
+                            yaw: 0.0,
+                            pitch: 0.0,
+                            on_target: false,
+                            intercept_point: None,
+                        },
+                    ))
+                    .id();
+                commands.entity(vessel_entity).add_child(turret);
+            }
+        }
+    }
+}
+
+/// Yaw and pitch in radians that point the mount's x axis along `direction`, given in the mount's frame
+fn aim_angles(direction: Vec3) -> (f32, f32) {
+    let yaw = (-direction.z).atan2(direction.x);
+    let pitch = direction
+        .y
+        .atan2(Vec2::new(direction.x, direction.z).length());
+    (yaw, pitch)
+}
+
+fn turn_towards(current: f32, desired: f32, max_step: f32) -> f32 {
+    current + (desired - current).clamp(-max_step, max_step)
+}
+
+/// The vessel carrying a turret, with the target and velocities the turret leads
+#[derive(QueryData)]
+pub struct TurretMount {
+    transform: &'static Transform,
+    velocity: &'static VelocityVector,
+    vessel_definition: &'static VesselDefinition,
+    target: Option<&'static Target>,
+}
+
+/// Vessels a turret can be aimed at, kept apart from the turrets' own transforms
+type TurretTargets = (With<VesselID>, Without<Turret>);
+
+/// Turns turrets towards their vessel's locked target within their limits and traverse speed
+pub fn track_turrets(
+    time: Res<Time>,
+    mut turrets: Query<(&mut Turret, &mut Transform, &Parent)>,
+    vessels: Query<TurretMount, Without<Turret>>,
+    target_transforms: Query<(&Transform, &VelocityVector), TurretTargets>,
+) {
+    for (mut turret, mut turret_transform, parent) in turrets.iter_mut() {
+        let Ok(TurretMountItem {
+            transform: vessel_transform,
+            velocity: vessel_velocity,
+            vessel_definition,
+            target,
+        }) = vessels.get(parent.get())
+        else {
+            continue;
+        };
+        let Some(hardpoint) = vessel_definition
+            .hardpoints
+            .get(&turret.weapons_type)
+            .and_then(|hardpoints| hardpoints.get(turret.hardpoint_index))
+        else {
+            continue;
+        };
+        let Some(turret_properties) = &hardpoint.turret else {
+            continue;
+        };
+        let yaw_limits = turret_properties.yaw_limits * PI_OVER_180;
+        let pitch_limits = turret_properties.pitch_limits * PI_OVER_180;
+
+        let mount_rotation = vessel_transform.rotation * hardpoint.transform.rotation;
+        let mount_position = vessel_transform.transform_point(hardpoint.transform.translation);
+        let desired_angles = turret
+            .intercept_point
+            .or_else(|| {
+                target
+                    .filter(|_| turret.weapons_type != WeaponsType::PointDefence)
+                    .and_then(|target| target_transforms.get(target.0).ok())
+                    .map(|(target_transform, target_velocity)| {
+                        lead_point(
+                            mount_position,
+                            vessel_transform.rotation * vessel_velocity.linear_velocity,
+                            target_transform.translation,
+                            target_transform.rotation * target_velocity.linear_velocity,
+                            vessel_definition
+                                .weapon_stats(&turret.weapons_type)
+                                .map_or(0.0, |weapon_stats| weapon_stats.velocity.length()),
+                        )
+                    })
+            })
+            .map(|aim_point| aim_angles(mount_rotation.inverse() * (aim_point - mount_position)));
+        let in_arc = desired_angles.is_some_and(|(yaw, pitch)| {
+            (yaw_limits.x..=yaw_limits.y).contains(&yaw)
+                && (pitch_limits.x..=pitch_limits.y).contains(&pitch)
+        });
+        let (desired_yaw, desired_pitch) = desired_angles.unwrap_or((0.0, 0.0));
+
+        let max_step = turret_properties.traverse_speed * PI_OVER_180 * time.delta_seconds();
+        turret.yaw = turn_towards(
+            turret.yaw,
+            desired_yaw.clamp(yaw_limits.x, yaw_limits.y),
+            max_step,
+        );
+        turret.pitch = turn_towards(
+            turret.pitch,
+            desired_pitch.clamp(pitch_limits.x, pitch_limits.y),
+            max_step,
+        );
+        turret.on_target = in_arc
+            && (desired_yaw - turret.yaw).abs() <= FIRING_TOLERANCE * PI_OVER_180
+            && (desired_pitch - turret.pitch).abs() <= FIRING_TOLERANCE * PI_OVER_180;
+
+        turret_transform.translation = hardpoint.transform.translation;
+        turret_transform.rotation = hardpoint.transform.rotation
+            * Quat::from_rotation_y(turret.yaw)
+            * Quat::from_rotation_z(turret.pitch);
+    }
+}
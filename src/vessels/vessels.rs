@@ -1,33 +1,79 @@
 use std::collections::HashMap;
 
-use bevy::prelude::Component;
+use bevy::{
+    prelude::{Asset, Component, Vec3},
+    reflect::TypePath,
+};
+use serde::Deserialize;
 
 use crate::player::player::Player;
 
 use super::{
+    defences::{ArmourProperties, ShieldProperties},
     movements::MovementProperties,
-    weapons::{Hardpoint, WeaponsType},
+    subsystems::SubsystemProperties,
+    targeting::SensorProperties,
+    weapon_groups::WeaponGroup,
+    weapons::{Hardpoint, WeaponStats, WeaponsType},
 };
 
-#[derive(Component, PartialEq, Eq, Clone)]
+#[derive(Component, PartialEq, Eq, Clone, Debug)]
 pub struct VesselID {
     pub player: Player,
     pub id: u32,
 }
-#[derive(Clone)]
+#[derive(Clone, Debug, Deserialize)]
 pub enum VesselClass {
     Cruiser,
+    /// Small craft that point defence engages like incoming missiles
+    Fighter,
 }
-#[derive(Clone)]
+#[derive(Clone, Debug, Deserialize)]
 pub enum Faction {
     Greek,
 }
 
-#[derive(Component, Clone)]
+/// Everything that makes up a type of vessel, loaded from `*.vessel.ron` files under `assets/vessels`
+#[derive(Asset, TypePath, Component, Clone, Deserialize)]
 pub struct VesselDefinition {
     pub class: VesselClass,
     pub faction: Faction,
     pub model_path: String,
     pub movement_properties: MovementProperties,
+    /// Mass of the rigid body, thrust forces scale with it
+    pub mass: f32,
+    /// Half extents of the box collider around the model
+    pub collider_half_extents: Vec3,
+    /// Hull integrity of a freshly spawned vessel
+    pub hull: f32,
+    #[serde(default)]
+    pub shields: Option<ShieldProperties>,
+    #[serde(default)]
+    pub armour: Option<ArmourProperties>,
     pub hardpoints: HashMap<WeaponsType, Vec<Hardpoint>>,
+    /// Stats of every weapons type mounted on the hardpoints
+    pub weapons: Vec<WeaponStats>,
+    /// Distance ahead of the nose at which fixed hardpoints converge while no target is locked
+    #[serde(default)]
+    pub convergence_distance: Option<f32>,
+    /// Weapons types fired together by one trigger
+    #[serde(default)]
+    pub weapon_groups: Vec<WeaponGroup>,
+    /// Heat the weapons may build up before they are throttled
+    pub heat_capacity: f32,
+    /// Heat shed per second
+    pub heat_dissipation: f32,
+    /// Magazine size per weapons type, types without an entry have unlimited ammunition
+    #[serde(default)]
+    pub ammunition: HashMap<WeaponsType, u32>,
+    pub sensors: SensorProperties,
+    #[serde(default)]
+    pub subsystems: Vec<SubsystemProperties>,
+}
+impl VesselDefinition {
+    pub fn weapon_stats(&self, weapons_type: &WeaponsType) -> Option<&WeaponStats> {
+        self.weapons
+            .iter()
+            .find(|weapon_stats| weapon_stats.weapons_type == *weapons_type)
+    }
 }
@@ -0,0 +1,62 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use super::{
+    vessels::{VesselDefinition, VesselID},
+    weapons::{WeaponsFireEvent, WeaponsType},
+};
+
+/// How the mounts of a weapons type answer a pull of the trigger
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Deserialize)]
+pub enum FireMode {
+    /// Every mount that is ready fires
+    #[default]
+    All,
+    /// Mounts take turns, one shot per trigger pull
+    Alternating,
+    /// Mounts only fire together, once every one of them is ready
+    Salvo,
+}
+
+/// Weapons types of a vessel that share one trigger
+#[derive(Clone, Deserialize)]
+pub struct WeaponGroup {
+    pub name: String,
+    pub weapons: Vec<WeaponsType>,
+    #[serde(default)]
+    pub fire_mode: FireMode,
+}
+
+/// Pulls the trigger of a weapon group, indexed like the definition's `weapon_groups`
+#[derive(Event)]
+pub struct WeaponGroupFireEvent {
+    pub vessel_id: VesselID,
+    pub group: usize,
+}
+
+/// Fires the weapons types of triggered groups in the group's fire mode
+pub fn fire_weapon_groups(
+    mut group_fire_events: EventReader<WeaponGroupFireEvent>,
+    mut weapons_fire_events: EventWriter<WeaponsFireEvent>,
+    vessels: Query<(&VesselID, &VesselDefinition)>,
+) {
+    for group_fire_event in group_fire_events.read() {
+        let Some(weapon_group) = vessels
+            .iter()
+            .find(|(vessel_id, _)| **vessel_id == group_fire_event.vessel_id)
+            .and_then(|(_, vessel_definition)| {
+                vessel_definition.weapon_groups.get(group_fire_event.group)
+            })
+        else {
+            continue;
+        };
+        for weapons_type in weapon_group.weapons.iter() {
+            weapons_fire_events.send(WeaponsFireEvent {
+                vessel_id: group_fire_event.vessel_id.clone(),
+                weapons_type: weapons_type.clone(),
+                fire_mode: weapon_group.fire_mode,
+                aim_at_target: true,
+            });
+        }
+    }
+}
@@ -1,41 +1,313 @@
 use std::f32::consts::PI;
 
-use bevy::prelude::*;
+use bevy::{
+    ecs::{query::QueryData, system::SystemParam},
+    prelude::*,
+    utils::HashMap,
+};
+use bevy_rapier3d::prelude::*;
+use serde::Deserialize;
 
-use super::vessels::{VesselDefinition, VesselID};
+use super::{
+    beams::{spawn_beam, update_beams, Beam, BeamProperties},
+    damage::Hull,
+    gunnery::{lead_point, update_lead_indicators},
+    missiles::{detonate_missiles, guide_missiles, GuidanceProperties, Guided},
+    movements::VelocityVector,
+    point_defence::{assign_point_defence, fire_point_defence},
+    subsystems::Subsystems,
+    turrets::{spawn_turrets, track_turrets, Turret, TurretProperties},
+    vessels::{VesselDefinition, VesselID},
+    weapon_groups::{fire_weapon_groups, FireMode, WeaponGroupFireEvent},
+};
 
-#[derive(Clone)]
+#[derive(Clone, Deserialize)]
 pub struct Hardpoint {
     pub transform: Transform,
+    /// Seconds between two shots of this mount
+    pub cooldown: f32,
+    /// Heat added to the vessel's heat budget by each shot
+    pub heat: f32,
+    /// Degrees off the transform a fixed mount can swivel to converge or lead, it fires straight along
+    /// the transform at aim points outside this cone
+    #[serde(default = "default_gimbal")]
+    pub gimbal: f32,
+    /// Turreted mounts track the locked target, fixed ones fire along the transform
+    #[serde(default)]
+    pub turret: Option<TurretProperties>,
 }
 
-#[derive(Clone, PartialEq, Eq, Hash)]
+/// Fixed mounts without a gimbal of their own still converge and lead within a few degrees
+fn default_gimbal() -> f32 {
+    5.0
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Deserialize)]
 pub enum WeaponsType {
     Plasma,
+    Missile,
+    Torpedo,
+    Beam,
+    Lance,
+    /// Turreted guns that shoot down incoming missiles on their own
+    PointDefence,
 }
 
-struct Target(Entity);
-#[derive(Clone, Component)]
+/// The vessel's locked target, weapons fired with `aim_at_target` are pointed at it
+#[derive(Component)]
+pub struct Target(pub Entity);
+/// Ballistics of a weapons type, loaded with the [`VesselDefinition`] that mounts it
+#[derive(Clone, Component, Deserialize)]
 pub struct WeaponStats {
     pub weapons_type: WeaponsType,
     pub color: LinearRgba,
     pub velocity: Vec3,
+    pub damage: f32,
+    /// Distance a shot travels relative to its launcher before it dissipates
+    pub range: f32,
+    /// Self-propelled projectiles home on the launcher's target
+    #[serde(default)]
+    pub guidance: Option<GuidanceProperties>,
+    /// Continuous weapons fire a raycast beam instead of launching projectiles
+    #[serde(default)]
+    pub beam: Option<BeamProperties>,
+}
+
+/// Marks a fired shot and remembers the vessel it was launched from
+#[derive(Component)]
+pub struct Projectile {
+    pub launcher: Entity,
+    pub vessel_id: VesselID,
+    pub lifetime: Timer,
+}
+
+/// Runtime state of a vessel's weapons, initialised from its [`VesselDefinition`]
+#[derive(Component)]
+pub struct WeaponsState {
+    /// Remaining cooldown in seconds per hardpoint, indexed like the definition's hardpoints
+    pub cooldowns: HashMap<WeaponsType, Vec<f32>>,
+    pub heat: f32,
+    /// Rounds left per weapons type, types without an entry never run dry
+    pub ammunition: HashMap<WeaponsType, u32>,
+    /// Hardpoint index the next alternating shot of a weapons type starts from
+    pub next_mounts: HashMap<WeaponsType, usize>,
+}
+impl WeaponsState {
+    pub fn new(vessel_definition: &VesselDefinition) -> Self {
+        WeaponsState {
+            cooldowns: HashMap::default(),
+            heat: 0.0,
+            ammunition: vessel_definition
+                .ammunition
+                .iter()
+                .map(|(weapons_type, rounds)| (weapons_type.clone(), *rounds))
+                .collect(),
+            next_mounts: HashMap::default(),
+        }
+    }
+    /// Weapons state of a reloaded definition, keeping heat, rounds and cooldowns of the weapons still
+    /// mounted up to the new maximums
+    pub fn refitted(&self, vessel_definition: &VesselDefinition) -> Self {
+        let mut weapons_state = WeaponsState::new(vessel_definition);
+        weapons_state.heat = self.heat.min(vessel_definition.heat_capacity);
+        for (weapons_type, rounds) in weapons_state.ammunition.iter_mut() {
+            if let Some(rounds_left) = self.ammunition.get(weapons_type) {
+                *rounds = (*rounds).min(*rounds_left);
+            }
+        }
+        for (weapons_type, hardpoints) in vessel_definition.hardpoints.iter() {
+            if let Some(cooldowns) = self.cooldowns.get(weapons_type) {
+                weapons_state.cooldowns.insert(
+                    weapons_type.clone(),
+                    cooldowns
+                        .iter()
+                        .zip(hardpoints.iter())
+                        .map(|(cooldown, hardpoint)| cooldown.min(hardpoint.cooldown))
+                        .collect(),
+                );
+            }
+            if let Some(next_mount) = self.next_mounts.get(weapons_type) {
+                weapons_state
+                    .next_mounts
+                    .insert(weapons_type.clone(), *next_mount);
+            }
+        }
+        weapons_state
+    }
+    /// Whether every hardpoint of a weapons type can fire at once
+    fn salvo_ready(
+        &self,
+        weapons_type: &WeaponsType,
+        hardpoints: &[Hardpoint],
+        heat_capacity: f32,
+    ) -> bool {
+        let salvo_heat: f32 = hardpoints.iter().map(|hardpoint| hardpoint.heat).sum();
+        if self.heat + salvo_heat > heat_capacity {
+            return false;
+        }
+        if self
+            .ammunition
+            .get(weapons_type)
+            .is_some_and(|rounds| (*rounds as usize) < hardpoints.len())
+        {
+            return false;
+        }
+        self.cooldowns.get(weapons_type).map_or(true, |cooldowns| {
+            cooldowns.iter().all(|cooldown| *cooldown <= 0.0)
+        })
+    }
+    /// Fires the hardpoint if it is off cooldown and both heat budget and ammunition allow it, the cooldown
+    /// stretches as the `efficiency` of the subsystems feeding the hardpoint drops
+    fn try_fire(
+        &mut self,
+        weapons_type: &WeaponsType,
+        hardpoint_index: usize,
+        hardpoint: &Hardpoint,
+        efficiency: f32,
+        heat_capacity: f32,
+    ) -> bool {
+        if self.heat + hardpoint.heat > heat_capacity {
+            return false;
+        }
+        if self.ammunition.get(weapons_type) == Some(&0) {
+            return false;
+        }
+        let cooldowns = self.cooldowns.entry(weapons_type.clone()).or_default();
+        if cooldowns.len() <= hardpoint_index {
+            cooldowns.resize(hardpoint_index + 1, 0.0);
+        }
+        if cooldowns[hardpoint_index] > 0.0 {
+            return false;
+        }
+        cooldowns[hardpoint_index] = hardpoint.cooldown / efficiency;
+        self.heat += hardpoint.heat;
+        if let Some(rounds) = self.ammunition.get_mut(weapons_type) {
+            *rounds -= 1;
+        }
+        true
+    }
+}
+
+/// Mesh and material shared by every projectile of a weapons type and colour, definitions that colour
+/// their shots differently or are reloaded with a new colour get their own
+#[derive(Resource, Default)]
+struct ProjectileAssets(HashMap<ProjectileLook, (Handle<Mesh>, Handle<StandardMaterial>)>);
+/// Weapons type and the bits of the colour a projectile is drawn in
+type ProjectileLook = (WeaponsType, [u32; 4]);
+
+/// Creates projectile meshes and materials the first time a look is fired and shares them afterwards
+#[derive(SystemParam)]
+struct ProjectileMeshes<'w> {
+    meshes: ResMut<'w, Assets<Mesh>>,
+    materials: ResMut<'w, Assets<StandardMaterial>>,
+    projectile_assets: ResMut<'w, ProjectileAssets>,
+}
+impl<'w> ProjectileMeshes<'w> {
+    fn get(&mut self, weapon_stats: &WeaponStats) -> (Handle<Mesh>, Handle<StandardMaterial>) {
+        let ProjectileMeshes {
+            meshes,
+            materials,
+            projectile_assets,
+        } = self;
+        projectile_assets
+            .0
+            .entry((
+                weapon_stats.weapons_type.clone(),
+                weapon_stats.color.to_f32_array().map(f32::to_bits),
+            ))
+            .or_insert_with(|| match weapon_stats.weapons_type {
+                WeaponsType::Plasma | WeaponsType::PointDefence => (
+                    meshes.add(Cylinder::new(0.01, 1.0)),
+                    materials.add(StandardMaterial {
+                        emissive: weapon_stats.color, // 4. Put something bright in a dark environment to see the effect
+                        ..default()
+                    }),
+                ),
+                WeaponsType::Missile => (
+                    meshes.add(Capsule3d::new(0.15, 1.5)),
+                    materials.add(StandardMaterial {
+                        base_color: Color::srgb(0.7, 0.7, 0.7),
+                        emissive: weapon_stats.color,
+                        ..default()
+                    }),
+                ),
+                WeaponsType::Torpedo => (
+                    meshes.add(Capsule3d::new(0.4, 4.0)),
+                    materials.add(StandardMaterial {
+                        base_color: Color::srgb(0.5, 0.5, 0.55),
+                        emissive: weapon_stats.color,
+                        ..default()
+                    }),
+                ),
+                WeaponsType::Beam | WeaponsType::Lance => (
+                    meshes.add(Cylinder::new(0.5, 1.0)),
+                    materials.add(StandardMaterial {
+                        emissive: weapon_stats.color,
+                        unlit: true,
+                        ..default()
+                    }),
+                ),
+            })
+            .clone()
+    }
+}
+
+/// A vessel firing its weapons with what aiming and firing depend on
+#[derive(QueryData)]
+#[query_data(mutable)]
+struct FiringVessel {
+    entity: Entity,
+    vessel_id: &'static VesselID,
+    vessel_definition: &'static VesselDefinition,
+    transform: &'static Transform,
+    velocity: &'static Velocity,
+    weapons_state: &'static mut WeaponsState,
+    target: Option<&'static Target>,
+    subsystems: Option<&'static Subsystems>,
 }
 
 #[derive(Event)]
-pub struct WeaponsFireEvent(pub (VesselID, WeaponStats));
+pub struct WeaponsFireEvent {
+    pub vessel_id: VesselID,
+    pub weapons_type: WeaponsType,
+    pub fire_mode: FireMode,
+    /// Point the shots at the vessel's locked [`Target`] instead of along the hardpoint
+    pub aim_at_target: bool,
+}
 
 pub struct WeaponsPlugin;
 
 impl Plugin for WeaponsPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<WeaponsFireEvent>()
-            .add_systems(Update, (fire_weapon, move_projectile));
+            .add_event::<WeaponGroupFireEvent>()
+            .init_resource::<ProjectileAssets>()
+            .add_systems(
+                Update,
+                (
+                    spawn_turrets,
+                    fire_weapon_groups.before(fire_weapon),
+                    assign_point_defence.before(track_turrets),
+                    track_turrets.before(fire_weapon),
+                    fire_point_defence.after(track_turrets).before(fire_weapon),
+                    fire_weapon,
+                    cool_weapons,
+                    expire_projectiles,
+                    guide_missiles,
+                    detonate_missiles.after(guide_missiles),
+                    update_beams.after(fire_weapon),
+                    update_lead_indicators,
+                ),
+            );
     }
 }
+/// World transform a shot leaves the hardpoint with, turned to fly towards `aim_point` if there is one
+/// within `gimbal` radians of the hardpoint's own direction
 fn calculate_launch_transform(
     vessel_transform: &Transform,
     hardpoint_transform: &Transform,
+    aim_point: Option<Vec3>,
+    gimbal: f32,
 ) -> Transform {
     let mut absolute_hardpoint_transform = hardpoint_transform.clone();
     absolute_hardpoint_transform.translation += vessel_transform.translation;
@@ -43,51 +315,220 @@ fn calculate_launch_transform(
         .rotate_around(vessel_transform.translation, vessel_transform.rotation);
 
     absolute_hardpoint_transform.rotate_local_z(PI / 2.0);
-    absolute_hardpoint_transform
+    let boresight = vessel_transform.rotation * hardpoint_transform.rotation * Vec3::X;
+    let Some(direction) = aim_point
+        .and_then(|aim_point| Dir3::new(aim_point - absolute_hardpoint_transform.translation).ok())
+        .filter(|direction| direction.angle_between(boresight) <= gimbal)
+    else {
+        return absolute_hardpoint_transform;
+    };
+    absolute_hardpoint_transform.with_rotation(
+        Quat::from_rotation_arc(Vec3::X, *direction) * Quat::from_rotation_z(PI / 2.0),
+    )
 }
 
 fn fire_weapon(
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut projectile_meshes: ProjectileMeshes,
     mut shots_fired: EventReader<WeaponsFireEvent>,
-    vessels: Query<(&VesselID, &VesselDefinition, &Transform)>,
+    mut vessels: Query<FiringVessel>,
+    target_transforms: Query<(&Transform, &VelocityVector), With<VesselID>>,
+    turrets: Query<(Entity, &Turret, &Transform, &Parent)>,
 ) {
     for shot_fired in shots_fired.read() {
-        let (firing_vessel_id, weapon_stats) = shot_fired.0.clone();
-        if let Some((vessel_definition, vessel_transform)) = vessels
-            .iter()
-            .filter(|(vessel_id, _, _)| **vessel_id == firing_vessel_id)
-            .map(|(_, vessel_definition, vessel_transform)| (vessel_definition, vessel_transform))
-            .into_iter()
-            .next()
+        let firing_vessel_id = shot_fired.vessel_id.clone();
+        let Some(vessel) = vessels
+            .iter_mut()
+            .find(|vessel| *vessel.vessel_id == firing_vessel_id)
+        else {
+            continue;
+        };
+        let FiringVesselItem {
+            entity: vessel_entity,
+            vessel_definition,
+            transform: vessel_transform,
+            velocity: vessel_velocity,
+            mut weapons_state,
+            target,
+            subsystems,
+            ..
+        } = vessel;
+        let Some(relevant_hardpoints) = vessel_definition.hardpoints.get(&shot_fired.weapons_type)
+        else {
+            continue;
+        };
+        let Some(weapon_stats) = vessel_definition
+            .weapon_stats(&shot_fired.weapons_type)
+            .cloned()
+        else {
+            continue;
+        };
+        let (projectile_mesh, projectile_material) = projectile_meshes.get(&weapon_stats);
+        let lifetime = match &weapon_stats.guidance {
+            Some(guidance) => {
+                guidance.flight_time(weapon_stats.velocity.length(), weapon_stats.range)
+            }
+            None => weapon_stats.range / weapon_stats.velocity.length(),
+        };
+        // Fixed mounts lead the locked target, without one they converge ahead of the nose
+        let aim_point = match target
+            .filter(|_| shot_fired.aim_at_target)
+            .and_then(|target| target_transforms.get(target.0).ok())
         {
-            if let Some((_, relevant_hardpoints)) = vessel_definition
-                .hardpoints
-                .iter()
-                .filter(|(weapons_type, _)| **weapons_type == weapon_stats.clone().weapons_type)
-                .into_iter()
-                .next()
-            {
-                for hardpoint in relevant_hardpoints {
-                    let launch_point =
-                        calculate_launch_transform(vessel_transform, &hardpoint.transform);
-                    match weapon_stats.weapons_type {
-                        // let launch_position = calculate_launch_transform(vessel_transform, )
-                        WeaponsType::Plasma => {
-                            commands.spawn((
-                                PbrBundle {
-                                    mesh: meshes.add(Cylinder::new(0.01, 1.0)),
-                                    material: materials.add(StandardMaterial {
-                                        emissive: weapon_stats.color.into(), // 4. Put something bright in a dark environment to see the effect
-                                        ..default()
-                                    }),
-                                    transform: launch_point,
-                                    ..default()
-                                },
-                                weapon_stats.clone(),
-                            ));
-                        }
+            Some((target_transform, target_velocity)) => Some(lead_point(
+                vessel_transform.translation,
+                vessel_velocity.linvel,
+                target_transform.translation,
+                target_transform.rotation * target_velocity.linear_velocity,
+                weapon_stats.velocity.length(),
+            )),
+            None => vessel_definition
+                .convergence_distance
+                .map(|distance| vessel_transform.transform_point(Vec3::X * distance)),
+        };
+        let mut mounts: Vec<_> = relevant_hardpoints
+            .iter()
+            .enumerate()
+            .map(|(hardpoint_index, hardpoint)| {
+                let turret = turrets.iter().find(|(_, turret, _, parent)| {
+                    parent.get() == vessel_entity
+                        && turret.weapons_type == weapon_stats.weapons_type
+                        && turret.hardpoint_index == hardpoint_index
+                });
+                // Damaged weapons subsystems slow the mounts they feed, destroyed ones silence them
+                let efficiency = subsystems.map_or(1.0, |subsystems| {
+                    subsystems.hardpoint_efficiency(
+                        vessel_definition,
+                        &weapon_stats.weapons_type,
+                        hardpoint.transform.translation,
+                    )
+                });
+                (hardpoint_index, hardpoint, turret, efficiency)
+            })
+            // Turrets hold their fire until the target is inside their arc
+            .filter(|(_, hardpoint, turret, efficiency)| {
+                *efficiency > 0.0
+                    && (hardpoint.turret.is_none()
+                        || turret.is_some_and(|(_, turret, _, _)| turret.on_target))
+            })
+            .collect();
+        match shot_fired.fire_mode {
+            FireMode::All => {}
+            FireMode::Alternating => {
+                // Start with the mount after the one that fired last
+                let next_mount = weapons_state
+                    .next_mounts
+                    .get(&weapon_stats.weapons_type)
+                    .copied()
+                    .unwrap_or(0);
+                let mount_count = relevant_hardpoints.len();
+                mounts.sort_by_key(|(hardpoint_index, _, _, _)| {
+                    (hardpoint_index + mount_count - next_mount % mount_count) % mount_count
+                });
+            }
+            FireMode::Salvo => {
+                if mounts.len() < relevant_hardpoints.len()
+                    || !weapons_state.salvo_ready(
+                        &weapon_stats.weapons_type,
+                        relevant_hardpoints,
+                        vessel_definition.heat_capacity,
+                    )
+                {
+                    continue;
+                }
+            }
+        }
+        let mut fired_mounts = 0;
+        for (hardpoint_index, hardpoint, turret, efficiency) in mounts {
+            if shot_fired.fire_mode == FireMode::Alternating && fired_mounts > 0 {
+                break;
+            }
+            if !weapons_state.try_fire(
+                &weapon_stats.weapons_type,
+                hardpoint_index,
+                hardpoint,
+                efficiency,
+                vessel_definition.heat_capacity,
+            ) {
+                continue;
+            }
+            fired_mounts += 1;
+            if shot_fired.fire_mode == FireMode::Alternating {
+                weapons_state
+                    .next_mounts
+                    .insert(weapon_stats.weapons_type.clone(), hardpoint_index + 1);
+            }
+            let launch_point = match turret {
+                Some((_, _, turret_transform, _)) => {
+                    calculate_launch_transform(vessel_transform, turret_transform, None, 0.0)
+                }
+                None => calculate_launch_transform(
+                    vessel_transform,
+                    &hardpoint.transform,
+                    aim_point,
+                    hardpoint.gimbal * PI / 180.0,
+                ),
+            };
+            if let Some(beam_properties) = &weapon_stats.beam {
+                spawn_beam(
+                    &mut commands,
+                    projectile_mesh.clone(),
+                    projectile_material.clone(),
+                    &weapon_stats,
+                    Beam {
+                        launcher: vessel_entity,
+                        vessel_id: firing_vessel_id.clone(),
+                        mount: hardpoint.transform,
+                        turret: turret.map(|(turret_entity, _, _, _)| turret_entity),
+                        charge: Timer::from_seconds(beam_properties.charge_time, TimerMode::Once),
+                        duration: Timer::from_seconds(beam_properties.duration, TimerMode::Once),
+                    },
+                );
+                continue;
+            }
+            // Shots leave the muzzle with the velocity of the hardpoint they are fired from
+            let launcher_velocity = vessel_velocity
+                .linear_velocity_at_point(launch_point.translation, vessel_transform.translation);
+            let mut projectile = commands.spawn((
+                PbrBundle {
+                    mesh: projectile_mesh.clone(),
+                    material: projectile_material.clone(),
+                    transform: launch_point,
+                    ..default()
+                },
+                weapon_stats.clone(),
+                Projectile {
+                    launcher: vessel_entity,
+                    vessel_id: firing_vessel_id.clone(),
+                    lifetime: Timer::from_seconds(lifetime, TimerMode::Once),
+                },
+                RigidBody::Dynamic,
+                Sensor,
+                ActiveEvents::COLLISION_EVENTS,
+                Ccd::enabled(),
+                Velocity::linear(
+                    launcher_velocity
+                        + launch_point.rotation
+                            * Quat::from_rotation_z(-PI / 2.0)
+                            * weapon_stats.velocity,
+                ),
+            ));
+            match weapon_stats.weapons_type {
+                // let launch_position = calculate_launch_transform(vessel_transform, )
+                WeaponsType::Plasma | WeaponsType::PointDefence => {
+                    projectile.insert(Collider::capsule_y(0.5, 0.01));
+                }
+                WeaponsType::Beam | WeaponsType::Lance => {}
+                WeaponsType::Missile | WeaponsType::Torpedo => {
+                    projectile.insert(Collider::capsule_y(1.0, 0.5));
+                    if let Some(guidance) = &weapon_stats.guidance {
+                        projectile.insert((
+                            Guided {
+                                target: target.map(|target| target.0),
+                                fuel: guidance.fuel,
+                            },
+                            Hull::new(guidance.hull),
+                        ));
                     }
                 }
             }
@@ -95,16 +536,28 @@ fn fire_weapon(
     }
 }
 
-fn move_projectile(mut projectiles: Query<(&mut Transform, &WeaponStats)>) {
-    for (mut projectile_transform, weapon_stats) in projectiles.iter_mut() {
-        let mut directed_velocity = Transform::from_translation(weapon_stats.velocity);
-        let mut rotation = projectile_transform.rotation * Quat::from_rotation_z(-PI / 2.);
-
-        directed_velocity.rotate_around(Vec3::ZERO, rotation);
-        directed_velocity.rotate_local_z(-PI / 2.0);
-        println!("{:?}", directed_velocity.translation);
-        println!("{:?}", projectile_transform.rotation);
+/// Counts down hardpoint cooldowns and dissipates accumulated heat
+fn cool_weapons(time: Res<Time>, mut vessels: Query<(&VesselDefinition, &mut WeaponsState)>) {
+    for (vessel_definition, mut weapons_state) in vessels.iter_mut() {
+        for cooldowns in weapons_state.cooldowns.values_mut() {
+            for cooldown in cooldowns.iter_mut() {
+                *cooldown = (*cooldown - time.delta_seconds()).max(0.0);
+            }
+        }
+        weapons_state.heat = (weapons_state.heat
+            - vessel_definition.heat_dissipation * time.delta_seconds())
+        .max(0.0);
+    }
+}
 
-        projectile_transform.translation += directed_velocity.translation
+fn expire_projectiles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut projectiles: Query<(Entity, &mut Projectile)>,
+) {
+    for (projectile_entity, mut projectile) in projectiles.iter_mut() {
+        if projectile.lifetime.tick(time.delta()).finished() {
+            commands.entity(projectile_entity).despawn_recursive();
+        }
     }
 }